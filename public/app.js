@@ -0,0 +1,48 @@
+// Expand/collapse for the blocks views.rs renders with `v-scope="{show_all: ...}"`.
+// Not petite-vue: only the directives the pages use, `v-bind:class` and `v-if`
+// with a `show_all` condition and `v-on:click` setting it. The
+// `app_js_covers_directives` test in src/assets.rs fails on anything else.
+(function () {
+  'use strict';
+
+  function condition(expr, showAll) {
+    return /^\s*!/.test(expr) ? !showAll : showAll;
+  }
+
+  function setup(scope) {
+    var showAll = /show_all:\s*true/.test(scope.getAttribute('v-scope'));
+    var bound = [].slice.call(scope.querySelectorAll('[v-bind\\:class]')).map(function (el) {
+      var m = /\?\s*'([^']*)'\s*:\s*'([^']*)'/.exec(el.getAttribute('v-bind:class')) || [];
+      return { el: el, base: el.className, yes: m[1] || '', no: m[2] || '' };
+    });
+    var templates = [].slice.call(scope.querySelectorAll('template[v-if]')).map(function (el) {
+      return { el: el, nodes: [] };
+    });
+
+    function render() {
+      bound.forEach(function (b) {
+        b.el.className = (b.base + ' ' + (showAll ? b.yes : b.no)).trim();
+      });
+      templates.forEach(function (t) {
+        t.nodes.forEach(function (node) { node.remove(); });
+        t.nodes = [];
+        if (condition(t.el.getAttribute('v-if'), showAll)) {
+          var content = t.el.content.cloneNode(true);
+          t.nodes = [].slice.call(content.childNodes);
+          t.el.parentNode.insertBefore(content, t.el.nextSibling);
+        }
+      });
+    }
+
+    scope.addEventListener('click', function (event) {
+      var target = event.target.closest('[v-on\\:click]');
+      if (target && scope.contains(target)) {
+        showAll = /=\s*true/.test(target.getAttribute('v-on:click'));
+        render();
+      }
+    });
+    render();
+  }
+
+  [].forEach.call(document.querySelectorAll('[v-scope]'), setup);
+})();
@@ -0,0 +1,470 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::Value;
+
+use crate::{
+    routes::{self, AppState},
+    types::{
+        AnswerPage, ApiResults, ArticlePage, ColumnPage, CommentsPage, Error, HotItem, Paging,
+        PeoplePage, PinPage, QuestionPage, SearchItem, TimelineItem, ZVideoPage,
+    },
+};
+
+/// Json counterpart of [`Error`], `{"error": {"status", "kind", "message"}}`.
+#[derive(Debug)]
+pub struct ApiError(Error);
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        ApiError(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        error!("{:?}", self.0);
+        let status = self.0.status();
+        let retries = match &self.0 {
+            Error::Retried { retries, .. } => *retries,
+            _ => 0,
+        };
+        let body = json!({
+            "error": {
+                "status": status.as_u16(),
+                "kind": kind(self.0.cause()),
+                "message": self.0.to_string(),
+                "retries": retries,
+            }
+        });
+        (status, Json(body)).into_response()
+    }
+}
+
+fn kind(err: &Error) -> &'static str {
+    match err {
+        Error::NotFound => "not_found",
+        Error::Unauthorized(_) => "unauthorized",
+        Error::AntiCrawler => "anti_crawler",
+        Error::RateLimited => "rate_limited",
+        Error::Busy => "busy",
+        Error::MissingEntity { .. } => "missing_entity",
+        Error::Upstream(_) | Error::Reqwest(_) => "upstream",
+        Error::Json(_) | Error::Any(_) => "internal",
+        Error::Retried { .. } => unreachable!("cause() unwraps retries"),
+    }
+}
+
+/// Points `next` and `previous` at our own api, empty when there is no such page.
+fn paging(paging: Paging, path: &str) -> Paging {
+    Paging {
+        next: match paging.is_end {
+            Some(false) => routes::paging_href(&paging.next, path),
+            _ => String::new(),
+        },
+        previous: match paging.is_start {
+            Some(false) => routes::paging_href(&paging.previous, path),
+            _ => String::new(),
+        },
+        ..paging
+    }
+}
+
+pub async fn recommend(
+    state: State<AppState>,
+    query: Query<Value>,
+) -> Result<Json<ApiResults<TimelineItem>>, ApiError> {
+    let mut results = routes::load_recommend(&state, query.0).await?;
+
+    results.paging = paging(results.paging, "/api/recommend");
+    Ok(Json(results))
+}
+
+pub async fn hot(
+    state: State<AppState>,
+    query: Query<Value>,
+) -> Result<Json<ApiResults<HotItem>>, ApiError> {
+    let category = query["category"].as_str().unwrap_or("total");
+    let mut results = routes::load_hot(&state, category).await?;
+
+    // the whole list comes in one page, and zhihu's cursor knows nothing of `category`
+    results.paging = Paging {
+        next: String::new(),
+        previous: String::new(),
+        ..results.paging
+    };
+    Ok(Json(results))
+}
+
+pub async fn question(
+    state: State<AppState>,
+    qid: Path<(String,)>,
+    query: Query<Value>,
+) -> Result<Json<QuestionPage>, ApiError> {
+    let qid = qid.0 .0;
+    let mut page = routes::load_question(&state, &qid, query.0).await?;
+
+    page.answers.paging = paging(page.answers.paging, &format!("/api/question/{}", qid));
+    Ok(Json(page))
+}
+
+pub async fn answer(
+    state: State<AppState>,
+    p: Path<(String, String)>,
+    query: Query<Value>,
+) -> Result<Json<AnswerPage>, ApiError> {
+    let (qid, aid) = p.0;
+
+    Ok(Json(
+        routes::load_answer(&state, &qid, &aid, query.0).await?,
+    ))
+}
+
+pub async fn article(
+    state: State<AppState>,
+    aid: Path<(String,)>,
+) -> Result<Json<ArticlePage>, ApiError> {
+    Ok(Json(routes::load_article(&state, &aid.0 .0).await?))
+}
+
+pub async fn pin(state: State<AppState>, id: Path<(String,)>) -> Result<Json<PinPage>, ApiError> {
+    Ok(Json(routes::load_pin(&state, &id.0 .0).await?))
+}
+
+pub async fn zvideo(
+    state: State<AppState>,
+    id: Path<(String,)>,
+) -> Result<Json<ZVideoPage>, ApiError> {
+    Ok(Json(routes::load_zvideo(&state, &id.0 .0).await?))
+}
+
+pub async fn column(
+    state: State<AppState>,
+    id: Path<(String,)>,
+    query: Query<Value>,
+) -> Result<Json<ColumnPage>, ApiError> {
+    let id = id.0 .0;
+    let mut page = routes::load_column(&state, &id, query.0).await?;
+
+    page.articles.paging = paging(page.articles.paging, &format!("/api/column/{}", id));
+    Ok(Json(page))
+}
+
+pub async fn people(
+    state: State<AppState>,
+    url_token: Path<(String,)>,
+    query: Query<Value>,
+) -> Result<Json<PeoplePage>, ApiError> {
+    people_tab(state, Path((url_token.0 .0, "answers".to_string())), query).await
+}
+
+pub async fn people_tab(
+    state: State<AppState>,
+    p: Path<(String, String)>,
+    query: Query<Value>,
+) -> Result<Json<PeoplePage>, ApiError> {
+    let (url_token, tab) = p.0;
+    let mut page = routes::load_people(&state, &url_token, &tab, query.0).await?;
+
+    page.items.paging = paging(
+        page.items.paging,
+        &format!("/api/people/{}/{}", url_token, tab),
+    );
+    Ok(Json(page))
+}
+
+pub async fn root_comment(
+    state: State<AppState>,
+    aid: Path<(String,)>,
+    query: Query<Value>,
+) -> Result<Json<CommentsPage>, ApiError> {
+    let aid = aid.0 .0;
+    let mut page = routes::load_root_comment(&state, &aid, query.0).await?;
+
+    page.comments.paging = paging(page.comments.paging, &format!("/api/comment/root/{}", aid));
+    Ok(Json(page))
+}
+
+pub async fn child_comment(
+    state: State<AppState>,
+    cid: Path<(String,)>,
+    query: Query<Value>,
+) -> Result<Json<CommentsPage>, ApiError> {
+    let cid = cid.0 .0;
+    let mut page = routes::load_child_comment(&state, &cid, query.0).await?;
+
+    page.comments.paging = paging(page.comments.paging, &format!("/api/comment/child/{}", cid));
+    Ok(Json(page))
+}
+
+pub async fn search(
+    state: State<AppState>,
+    query: Query<Value>,
+) -> Result<Json<ApiResults<SearchItem>>, ApiError> {
+    let mut results = routes::load_search(&state, &query.0).await?;
+
+    results.paging = paging(results.paging, "/api/search");
+    Ok(Json(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::backend::{
+        Endpoint, FixtureBackend, UpstreamRequest, UpstreamResponse, ZhihuBackend,
+    };
+
+    fn state() -> State<AppState> {
+        State(AppState::fixtures())
+    }
+
+    #[tokio::test]
+    async fn question_json() {
+        let Json(page) = question(state(), Path(("19550225".to_string(),)), Query(json!({})))
+            .await
+            .unwrap();
+
+        let value = serde_json::to_value(&page).unwrap();
+        assert_eq!(value["question"]["id"], "19550225");
+        assert_eq!(value["answers"]["data"][0]["id"], "1000001");
+        assert_eq!(value["archived_at"], Value::Null);
+        assert_eq!(
+            value["answers"]["paging"]["next"],
+            "/api/question/19550225?cursor=c2&limit=5&offset=0&order=default"
+        );
+        assert_eq!(value["answers"]["paging"]["previous"], "");
+    }
+
+    #[tokio::test]
+    async fn search_json() {
+        let Json(results) = search(state(), Query(json!({ "q": "rust" })))
+            .await
+            .unwrap();
+
+        let value = serde_json::to_value(&results).unwrap();
+        assert_eq!(value["data"][0]["type"], "relevant_query");
+        assert_eq!(value["data"][1]["type"], "search_result");
+        assert_eq!(value["data"][1]["data"]["id"], "1000001");
+        // only our own fields, nothing passed through from zhihu
+        assert!(value["data"][1]["data"].get("thumbnail_info").is_none());
+    }
+
+    /// The fixtures with a shorter second recommend page.
+    struct SecondPage(FixtureBackend);
+
+    #[async_trait]
+    impl ZhihuBackend for SecondPage {
+        async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+            let second = request.endpoint == Endpoint::Recommend
+                && request
+                    .query
+                    .as_ref()
+                    .is_some_and(|query| query["page_number"] == "2");
+            let mut response = self.0.fetch(request).await?;
+            if second {
+                let mut value: Value = serde_json::from_str(&response.body)?;
+                value["data"].as_array_mut().unwrap().remove(0);
+                response.body = value.to_string();
+            }
+            Ok(response)
+        }
+    }
+
+    /// The fixtures until switched off, then only 503s.
+    #[derive(Default)]
+    struct Outage {
+        down: AtomicBool,
+    }
+
+    #[async_trait]
+    impl ZhihuBackend for Outage {
+        async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+            if self.down.load(Ordering::SeqCst) {
+                return Ok(UpstreamResponse {
+                    status: 503,
+                    body: String::new(),
+                });
+            }
+            FixtureBackend::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+                .fetch(request)
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn comments_from_archive() {
+        let outage = Arc::new(Outage::default());
+        let state = State(AppState {
+            backend: outage.clone(),
+            ..AppState::fixtures()
+        });
+        let aid = || Path(("1000001".to_string(),));
+        let cid = || Path(("6000001".to_string(),));
+
+        let Json(root) = root_comment(state.clone(), aid(), Query(json!({})))
+            .await
+            .unwrap();
+        let Json(child) = child_comment(state.clone(), cid(), Query(json!({})))
+            .await
+            .unwrap();
+        assert_eq!(root.archived_at, None);
+        assert_eq!(child.comments.data[0].id, "6000002");
+
+        outage.down.store(true, Ordering::SeqCst);
+        let Json(archived) = root_comment(state.clone(), aid(), Query(json!({})))
+            .await
+            .unwrap();
+        assert!(archived.archived_at.is_some());
+        assert_eq!(archived.comments.data, root.comments.data);
+
+        let Json(archived) = child_comment(state.clone(), cid(), Query(json!({})))
+            .await
+            .unwrap();
+        assert!(archived.archived_at.is_some());
+        // the root as archived with its answer's comments
+        assert_eq!(archived.root.unwrap().id, "6000001");
+        assert_eq!(archived.comments.data, child.comments.data);
+        assert_eq!(archived.comments.paging.next, "");
+
+        // nothing archived, the error stays
+        let err = child_comment(state, Path(("6000009".to_string(),)), Query(json!({})))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.0.cause(), Error::Upstream(503)));
+    }
+
+    #[tokio::test]
+    async fn recommend_json() {
+        let state = State(AppState {
+            backend: Arc::new(SecondPage(FixtureBackend::new(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures"
+            )))),
+            ..AppState::fixtures()
+        });
+
+        let Json(first) = recommend(state.clone(), Query(json!({}))).await.unwrap();
+        assert_eq!(first.data.len(), 4);
+        assert_eq!(
+            first.paging.next,
+            "/api/recommend?session_token=abc&page_number=2"
+        );
+        assert_eq!(first.paging.previous, "");
+
+        // following our cursor asks zhihu for the next page
+        let query = json!({ "session_token": "abc", "page_number": "2" });
+        let Json(second) = recommend(state, Query(query)).await.unwrap();
+        assert_ne!(
+            first.data.iter().map(|item| &item.id).collect::<Vec<_>>(),
+            second.data.iter().map(|item| &item.id).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn hot_json() {
+        let Json(results) = hot(state(), Query(json!({}))).await.unwrap();
+        assert_eq!(results.data[0].question.id, "19550225");
+        assert_eq!(results.paging.next, "");
+        assert_eq!(results.paging.previous, "");
+
+        let err = hot(state(), Query(json!({ "category": "../recommend" })))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.0, Error::NotFound));
+    }
+
+    #[tokio::test]
+    async fn column_json() {
+        let Json(page) = column(state(), Path(("rust-notes".to_string(),)), Query(json!({})))
+            .await
+            .unwrap();
+
+        assert_eq!(page.column.title, "Rust 笔记");
+        assert_eq!(page.column.articles_count, 12);
+        assert_eq!(page.column.author.unwrap().url_token, "lisi");
+        assert_eq!(page.articles.data.len(), 2);
+        assert_eq!(
+            page.articles.paging.next,
+            "/api/column/rust-notes?limit=10&offset=10"
+        );
+        assert_eq!(page.articles.paging.previous, "");
+    }
+
+    #[tokio::test]
+    async fn people_json() {
+        let Json(page) = people(state(), Path(("zhangsan".to_string(),)), Query(json!({})))
+            .await
+            .unwrap();
+
+        assert_eq!(page.people.name, "张三");
+        assert_eq!(page.people.follower_count, 2048);
+        assert_eq!(page.tab, "answers");
+        assert_eq!(page.items.data.len(), 2);
+        assert_eq!(
+            page.items.paging.next,
+            "/api/people/zhangsan/answers?offset=20&limit=20&sort_by=created"
+        );
+
+        let Json(page) = people_tab(
+            state(),
+            Path(("zhangsan".to_string(), "pins".to_string())),
+            Query(json!({})),
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.items.data[0].type_, "pin");
+
+        let err = people_tab(
+            state(),
+            Path(("zhangsan".to_string(), "followers".to_string())),
+            Query(json!({})),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err.0, Error::NotFound));
+    }
+
+    #[tokio::test]
+    async fn pin_json() {
+        let Json(page) = pin(state(), Path(("4000001".to_string(),))).await.unwrap();
+
+        let value = serde_json::to_value(&page).unwrap();
+        assert_eq!(value["pin"]["id"], "4000001");
+        assert_eq!(value["pin"]["content"][1]["type"], "image");
+        assert_eq!(value["pin"]["origin_pin"]["id"], "4000000");
+    }
+
+    #[tokio::test]
+    async fn zvideo_json() {
+        let Json(page) = zvideo(state(), Path(("3000001".to_string(),)))
+            .await
+            .unwrap();
+
+        assert_eq!(page.zvideo.title, "一个视频");
+        // the fixture has no play urls, they come from the lens playlist
+        let playlist = &page.zvideo.video.playlist;
+        assert_eq!(
+            playlist.sd.as_ref().unwrap().url,
+            "https://vdn.vzuu.com/SD/5000001.mp4?auth_key=1"
+        );
+        assert!(playlist.ld.is_some() && playlist.hd.is_some());
+    }
+
+    #[tokio::test]
+    async fn error_json() {
+        let response = article(state(), Path(("404".to_string(),)))
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    }
+}
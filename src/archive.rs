@@ -0,0 +1,229 @@
+use std::{path::Path, sync::Mutex};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::types::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Question,
+    Answer,
+    Article,
+    Pin,
+    ZVideo,
+    Comment,
+}
+
+impl ArchiveKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveKind::Question => "question",
+            ArchiveKind::Answer => "answer",
+            ArchiveKind::Article => "article",
+            ArchiveKind::Pin => "pin",
+            ArchiveKind::ZVideo => "zvideo",
+            ArchiveKind::Comment => "comment",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Archived<T> {
+    pub value: T,
+    pub archived_at: i64,
+}
+
+/// Local sqlite copy of every entity we rendered, used when upstream fails
+/// or the entity has been deleted.
+///
+/// Write and read failures are only logged, the archive never breaks a page.
+pub struct Archive {
+    conn: Option<Mutex<Connection>>,
+}
+
+impl Archive {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let conn = Connection::open(path).map_err(anyhow::Error::from)?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn memory() -> Result<Self, Error> {
+        let conn = Connection::open_in_memory().map_err(anyhow::Error::from)?;
+        Self::init(conn)
+    }
+
+    pub fn disabled() -> Self {
+        Self { conn: None }
+    }
+
+    fn init(conn: Connection) -> Result<Self, Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS archive (
+                kind TEXT NOT NULL,
+                id TEXT NOT NULL,
+                parent TEXT NOT NULL,
+                data TEXT NOT NULL,
+                archived_at INTEGER NOT NULL,
+                PRIMARY KEY (kind, id)
+            );
+            CREATE INDEX IF NOT EXISTS archive_parent ON archive (kind, parent);",
+        )
+        .map_err(anyhow::Error::from)?;
+
+        Ok(Self {
+            conn: Some(Mutex::new(conn)),
+        })
+    }
+
+    /// Stores `value`, `parent` groups children such as the answers of a question.
+    pub fn put<T: Serialize>(&self, kind: ArchiveKind, id: &str, parent: &str, value: &T) {
+        let Some(conn) = &self.conn else {
+            return;
+        };
+
+        let result = serde_json::to_string(value)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| {
+                conn.lock()
+                    .unwrap()
+                    .execute(
+                        "INSERT OR REPLACE INTO archive (kind, id, parent, data, archived_at)
+                        VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![
+                            kind.as_str(),
+                            id,
+                            parent,
+                            data,
+                            chrono::Utc::now().timestamp()
+                        ],
+                    )
+                    .map_err(anyhow::Error::from)
+            });
+
+        if let Err(err) = result {
+            error!("archive {} {} error: {:?}", kind.as_str(), id, err);
+        }
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, kind: ArchiveKind, id: &str) -> Option<Archived<T>> {
+        let conn = self.conn.as_ref()?.lock().unwrap();
+
+        let row: Option<(String, i64)> = conn
+            .query_row(
+                "SELECT data, archived_at FROM archive WHERE kind = ?1 AND id = ?2",
+                params![kind.as_str(), id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .unwrap_or_else(|err| {
+                error!("read archive {} {} error: {:?}", kind.as_str(), id, err);
+                None
+            });
+
+        row.and_then(|(data, archived_at)| decode(kind, id, &data, archived_at))
+    }
+
+    /// All archived entities of `kind` under `parent`, most recently archived first.
+    pub fn children<T: DeserializeOwned>(
+        &self,
+        kind: ArchiveKind,
+        parent: &str,
+    ) -> Vec<Archived<T>> {
+        let Some(conn) = &self.conn else {
+            return vec![];
+        };
+        let conn = conn.lock().unwrap();
+
+        let rows = conn
+            .prepare(
+                "SELECT id, data, archived_at FROM archive
+                WHERE kind = ?1 AND parent = ?2 ORDER BY archived_at DESC",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map(params![kind.as_str(), parent], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })?
+                .collect::<Result<Vec<(String, String, i64)>, _>>()
+            })
+            .unwrap_or_else(|err| {
+                error!(
+                    "read archive {} of {} error: {:?}",
+                    kind.as_str(),
+                    parent,
+                    err
+                );
+                vec![]
+            });
+
+        rows.into_iter()
+            .filter_map(|(id, data, archived_at)| decode(kind, &id, &data, archived_at))
+            .collect()
+    }
+}
+
+fn decode<T: DeserializeOwned>(
+    kind: ArchiveKind,
+    id: &str,
+    data: &str,
+    archived_at: i64,
+) -> Option<Archived<T>> {
+    match serde_json::from_str(data) {
+        Ok(value) => Some(Archived { value, archived_at }),
+        Err(err) => {
+            error!("decode archive {} {} error: {:?}", kind.as_str(), id, err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Question, TimelineItem};
+
+    #[test]
+    fn put_and_get() {
+        let archive = Archive::memory().unwrap();
+        let question = Question {
+            id: "1".to_string(),
+            title: "title".to_string(),
+            ..Default::default()
+        };
+
+        archive.put(ArchiveKind::Question, "1", "", &question);
+        let archived: Archived<Question> = archive.get(ArchiveKind::Question, "1").unwrap();
+        assert_eq!(archived.value, question);
+        assert!(archived.archived_at > 0);
+
+        assert!(archive
+            .get::<Question>(ArchiveKind::Question, "2")
+            .is_none());
+        assert!(archive.get::<Question>(ArchiveKind::Answer, "1").is_none());
+    }
+
+    #[test]
+    fn children() {
+        let archive = Archive::memory().unwrap();
+        for (id, parent) in [("10", "1"), ("11", "1"), ("20", "2")] {
+            let answer = TimelineItem {
+                id: id.to_string(),
+                ..Default::default()
+            };
+            archive.put(ArchiveKind::Answer, id, parent, &answer);
+        }
+
+        let mut ids: Vec<String> = archive
+            .children::<TimelineItem>(ArchiveKind::Answer, "1")
+            .into_iter()
+            .map(|a| a.value.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["10", "11"]);
+
+        assert!(Archive::disabled()
+            .children::<TimelineItem>(ArchiveKind::Answer, "1")
+            .is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+};
+use http::{header, StatusCode};
+use once_cell::sync::OnceCell;
+
+use crate::cassette;
+
+/// Files compiled into the binary and served under `/static/`.
+const FILES: &[(&str, &str, &[u8])] = &[
+    (
+        "style.css",
+        "text/css; charset=utf-8",
+        include_bytes!("../public/style.css"),
+    ),
+    (
+        "app.js",
+        "text/javascript; charset=utf-8",
+        include_bytes!("../public/app.js"),
+    ),
+];
+
+static ASSETS: OnceCell<Vec<Asset>> = OnceCell::new();
+
+struct Asset {
+    name: &'static str,
+    /// `name` with the content hash before the extension, e.g. `style.0123abcd.css`.
+    hashed: String,
+    content_type: &'static str,
+    bytes: &'static [u8],
+}
+
+fn assets() -> &'static [Asset] {
+    ASSETS.get_or_init(|| {
+        FILES
+            .iter()
+            .map(|&(name, content_type, bytes)| {
+                let hash = format!("{:016x}", cassette::fnv1a(bytes));
+                let hashed = match name.rsplit_once('.') {
+                    Some((stem, ext)) => format!("{}.{}.{}", stem, &hash[..10], ext),
+                    None => format!("{}.{}", name, &hash[..10]),
+                };
+
+                Asset {
+                    name,
+                    hashed,
+                    content_type,
+                    bytes,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Url of a static file, changes whenever its content does.
+pub fn href(name: &str) -> String {
+    let asset = assets()
+        .iter()
+        .find(|asset| asset.name == name)
+        .unwrap_or_else(|| panic!("unknown asset {}", name));
+    format!("/static/{}", asset.hashed)
+}
+
+/// Only hashed names are served, so they can be cached forever.
+pub async fn serve(file: Path<(String,)>) -> Response {
+    match assets().iter().find(|asset| asset.hashed == file.0 .0) {
+        Some(asset) => (
+            [
+                (header::CONTENT_TYPE, asset.content_type),
+                (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+            ],
+            asset.bytes,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hashed_names() {
+        let href = href("style.css");
+        let file = href.strip_prefix("/static/").unwrap();
+        assert!(file.starts_with("style.") && file.ends_with(".css"));
+        assert_ne!(file, "style.css");
+
+        let response = serve(Path((file.to_string(),))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "public, max-age=31536000, immutable"
+        );
+
+        let response = serve(Path(("style.css".to_string(),))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // the published fnv-1a value, names survive toolchain updates
+        assert_eq!(cassette::fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        let hash = format!("{:016x}", cassette::fnv1a(FILES[0].2));
+        assert_eq!(file, format!("style.{}.css", &hash[..10]));
+    }
+
+    /// Every class the templates use needs a rule in the stylesheet.
+    #[test]
+    fn stylesheet_covers_classes() {
+        let css = std::str::from_utf8(FILES[0].2).unwrap();
+        let class_re = regex::Regex::new(
+            r#"\sclass="([^"]*)"|css!\("[^"]*", "([^"]*)"\)|"class", "([^"]*)"|\? '([^']*)' : '([^']*)'"#,
+        )
+        .unwrap();
+        let sources = [include_str!("views.rs"), include_str!("routes.rs")];
+        // zhihu's own markup matched by selectors, and `font-base` which never had a rule
+        let ignored = [
+            "comment_img",
+            "comment_sticker",
+            "external",
+            "highlight",
+            "language-rust",
+            "ztext",
+            "font-base",
+        ];
+
+        for source in sources {
+            for caps in class_re.captures_iter(source) {
+                let classes = caps.iter().skip(1).flatten();
+                for class in classes.flat_map(|m| m.as_str().split_whitespace()) {
+                    if ignored.contains(&class) {
+                        continue;
+                    }
+                    let escaped = class
+                        .replace('!', "\\!")
+                        .replace('/', "\\/")
+                        .replace('[', "\\[")
+                        .replace(']', "\\]")
+                        .replace('%', "\\%");
+                    let selector = format!(".{}", escaped);
+                    let found = css.match_indices(&selector).any(|(i, _)| {
+                        !css[i + selector.len()..]
+                            .starts_with(|c: char| c.is_alphanumeric() || c == '-')
+                    });
+                    assert!(found, "missing css rule for {}", class);
+                }
+            }
+        }
+    }
+
+    /// `app.js` is not a template engine, any other directive would silently do nothing.
+    #[test]
+    fn app_js_covers_directives() {
+        let directive_re =
+            regex::Regex::new(r#"(v-[a-z]+(?::[a-z]+)?)=(?:\[[^"]*)?"([^"]*)""#).unwrap();
+        let supported = [
+            ("v-scope", r"^\{\{show_all: \{show_all\} \}\}$"),
+            ("v-bind:class", r"^show_all \? '[^']*' : '[^']*'$"),
+            ("v-if", r"^!?show_all$"),
+            ("v-on:click", r"^show_all ?= ?(true|false)$"),
+        ];
+
+        for source in [include_str!("views.rs"), include_str!("routes.rs")] {
+            for caps in directive_re.captures_iter(source) {
+                let (name, expr) = (&caps[1], &caps[2]);
+                let pattern = supported
+                    .iter()
+                    .find(|(directive, _)| *directive == name)
+                    .unwrap_or_else(|| panic!("app.js does not handle {}", name))
+                    .1;
+                assert!(
+                    regex::Regex::new(pattern).unwrap().is_match(expr),
+                    "app.js does not handle {}=\"{}\"",
+                    name,
+                    expr
+                );
+            }
+        }
+    }
+}
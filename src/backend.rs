@@ -0,0 +1,627 @@
+use std::{
+    path::{Component, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+
+use crate::{cookies::CookiePool, proxy::ProxyConfig, types::Error};
+
+macro_rules! headers {
+	{ $($key:expr => $value:expr),+ } => {
+		{
+			let mut m = http::HeaderMap::new();
+			$(
+				if let Ok(val) = http::header::HeaderValue::from_str($value) {
+					m.insert($key, val);
+				}
+			)+
+			m
+		}
+	 };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endpoint {
+    Recommend,
+    HotList,
+    Question,
+    QuestionFeeds,
+    Answer,
+    Article,
+    Pin,
+    Column,
+    ColumnItems,
+    Member,
+    /// Answers, articles or pins of a member.
+    MemberItems,
+    #[serde(rename = "zvideo")]
+    ZVideo,
+    /// Play urls of a video, which expire after a while.
+    VideoPlaylist,
+    RootComment,
+    ChildComment,
+    Search,
+}
+
+impl Endpoint {
+    /// Html pages carry their data in `js-initialData`, everything else is a json api.
+    pub fn is_page(&self) -> bool {
+        matches!(
+            self,
+            Endpoint::Question | Endpoint::Answer | Endpoint::Article
+        )
+    }
+
+    /// Parses per endpoint settings like `recommend=10,article=86400`.
+    pub fn parse_spec<T>(spec: &str) -> anyhow::Result<Vec<(Endpoint, T)>>
+    where
+        T: std::str::FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        spec.split(',')
+            .filter(|item| !item.trim().is_empty())
+            .map(|item| {
+                let (endpoint, value) = item
+                    .split_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("invalid endpoint setting {:?}", item))?;
+                let endpoint: Endpoint = serde_json::from_value(json!(endpoint.trim()))
+                    .map_err(|_| anyhow::anyhow!("unknown endpoint {:?}", endpoint))?;
+                Ok((endpoint, value.trim().parse()?))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamRequest {
+    pub endpoint: Endpoint,
+    pub url: String,
+    pub query: Option<Value>,
+}
+
+impl UpstreamRequest {
+    pub fn new(endpoint: Endpoint, url: impl Into<String>) -> Self {
+        Self {
+            endpoint,
+            url: url.into(),
+            query: None,
+        }
+    }
+
+    pub fn query(mut self, query: &Value) -> Self {
+        if query.is_object() {
+            self.query = Some(query.clone());
+        }
+        self
+    }
+
+    /// Identifies the request by url and query, used to look up recorded or cached responses.
+    pub fn key(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{}?{}", self.url, query),
+            None => self.url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl UpstreamResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Zhihu answers suspicious clients with a captcha page or a 40362 error instead of data.
+    pub fn is_anti_crawler(&self) -> bool {
+        let challenged =
+            self.body.contains("account/unhuman") || self.body.contains("\"code\":40362");
+        challenged && (!self.is_success() || !self.body.contains("js-initialData"))
+    }
+
+    pub fn text(self) -> Result<String, Error> {
+        if self.is_anti_crawler() {
+            return Err(Error::AntiCrawler);
+        }
+        match self.status {
+            200..=299 => Ok(self.body),
+            404 | 410 => Err(Error::NotFound),
+            401 | 403 => Err(Error::Unauthorized(self.status)),
+            429 => Err(Error::RateLimited),
+            status => Err(Error::Upstream(status)),
+        }
+    }
+
+    pub fn json(self) -> Result<Value, Error> {
+        Ok(serde_json::from_str(&self.text()?)?)
+    }
+}
+
+/// Source of every upstream zhihu request made by the routes.
+///
+/// Implementors only need `fetch`, the endpoint methods build the urls.
+#[async_trait]
+pub trait ZhihuBackend: Send + Sync {
+    async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error>;
+
+    /// `query` is the one of a `paging.next` url, empty for the first page.
+    async fn recommend(&self, query: &Value) -> Result<Value, Error> {
+        let mut request = UpstreamRequest::new(
+            Endpoint::Recommend,
+            "https://www.zhihu.com/api/v3/feed/topstory/recommend",
+        );
+        // the first page keeps its query-less cache and cassette key
+        if query.as_object().is_some_and(|query| !query.is_empty()) {
+            request = request.query(query);
+        }
+        self.fetch(request).await?.json()
+    }
+
+    async fn hot_list(&self, category: &str) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::HotList,
+            format!(
+                "https://www.zhihu.com/api/v3/feed/topstory/hot-lists/{}",
+                category
+            ),
+        )
+        .query(&json!({ "limit": "50", "desktop": "true" }));
+        self.fetch(request).await?.json()
+    }
+
+    async fn question(&self, qid: &str) -> Result<String, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::Question,
+            format!("https://www.zhihu.com/question/{}", qid),
+        );
+        self.fetch(request).await?.text()
+    }
+
+    async fn question_feeds(&self, qid: &str, query: &Value) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::QuestionFeeds,
+            format!("https://www.zhihu.com/api/v4/questions/{}/feeds", qid),
+        )
+        .query(query);
+        self.fetch(request).await?.json()
+    }
+
+    async fn answer(&self, qid: &str, aid: &str, query: &Value) -> Result<String, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::Answer,
+            format!("https://www.zhihu.com/question/{}/answer/{}", qid, aid),
+        )
+        .query(query);
+        self.fetch(request).await?.text()
+    }
+
+    async fn article(&self, aid: &str) -> Result<String, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::Article,
+            format!("https://zhuanlan.zhihu.com/p/{}", aid),
+        );
+        self.fetch(request).await?.text()
+    }
+
+    async fn pin(&self, id: &str) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::Pin,
+            format!("https://www.zhihu.com/api/v4/pins/{}", id),
+        );
+        self.fetch(request).await?.json()
+    }
+
+    async fn column(&self, id: &str) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::Column,
+            format!("https://www.zhihu.com/api/v4/columns/{}", id),
+        );
+        self.fetch(request).await?.json()
+    }
+
+    async fn column_items(&self, id: &str, query: &Value) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::ColumnItems,
+            format!("https://www.zhihu.com/api/v4/columns/{}/items", id),
+        )
+        .query(query);
+        self.fetch(request).await?.json()
+    }
+
+    async fn member(&self, url_token: &str) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::Member,
+            format!("https://www.zhihu.com/api/v4/members/{}", url_token),
+        )
+        .query(&json!({
+            "include": "follower_count,answer_count,articles_count,pins_count,description,headline,avatar_url"
+        }));
+        self.fetch(request).await?.json()
+    }
+
+    /// `tab` is `answers`, `articles` or `pins`.
+    async fn member_items(
+        &self,
+        url_token: &str,
+        tab: &str,
+        query: &Value,
+    ) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::MemberItems,
+            format!("https://www.zhihu.com/api/v4/members/{}/{}", url_token, tab),
+        )
+        .query(query);
+        self.fetch(request).await?.json()
+    }
+
+    async fn zvideo(&self, id: &str) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::ZVideo,
+            format!("https://www.zhihu.com/api/v4/zvideos/{}", id),
+        );
+        self.fetch(request).await?.json()
+    }
+
+    async fn video_playlist(&self, video_id: &str) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::VideoPlaylist,
+            format!("https://lens.zhihu.com/api/v4/videos/{}", video_id),
+        );
+        self.fetch(request).await?.json()
+    }
+
+    async fn root_comment(&self, type_: &str, aid: &str, query: &Value) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::RootComment,
+            format!(
+                "https://www.zhihu.com/api/v4/comment_v5/{}/{}/root_comment",
+                type_, aid
+            ),
+        )
+        .query(query);
+        self.fetch(request).await?.json()
+    }
+
+    async fn child_comment(&self, cid: &str, query: &Value) -> Result<Value, Error> {
+        let request = UpstreamRequest::new(
+            Endpoint::ChildComment,
+            format!(
+                "https://www.zhihu.com/api/v4/comment_v5/comment/{}/child_comment",
+                cid
+            ),
+        )
+        .query(query);
+        self.fetch(request).await?.json()
+    }
+
+    async fn search(&self, query: &Value) -> Result<Value, Error> {
+        let request =
+            UpstreamRequest::new(Endpoint::Search, "https://www.zhihu.com/api/v4/search_v3")
+                .query(query);
+        self.fetch(request).await?.json()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub connect_timeout: Duration,
+    /// Whole request, from connecting until the body is read.
+    pub timeout: Duration,
+    pub proxy: ProxyConfig,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(15),
+            proxy: ProxyConfig::default(),
+        }
+    }
+}
+
+impl ClientOptions {
+    pub fn client(&self) -> Result<Client, Error> {
+        let mut builder = Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout);
+        // without proxy settings reqwest still honours HTTP_PROXY and friends
+        if !self.proxy.is_empty() {
+            builder = builder.proxy(self.proxy.rules()?.into_proxy());
+        }
+        Ok(builder.build()?)
+    }
+}
+
+pub struct ReqwestBackend {
+    client: Client,
+    cookies: Arc<CookiePool>,
+}
+
+impl ReqwestBackend {
+    pub fn new(cookies: Arc<CookiePool>, options: &ClientOptions) -> Result<Self, Error> {
+        Ok(Self {
+            client: options.client()?,
+            cookies,
+        })
+    }
+}
+
+#[async_trait]
+impl ZhihuBackend for ReqwestBackend {
+    async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+        let (index, cookie) = self.cookies.pick();
+        let mut builder = self.client.get(&request.url).headers(headers! {
+            "cookie" => cookie
+        });
+        if let Some(query) = &request.query {
+            builder = builder.query(query);
+        }
+        if request.endpoint == Endpoint::Search {
+            builder = builder
+                .header("x-zse-93", "101_3_3.0")
+                .header("x-zse-96", "2.0_");
+        }
+
+        let response = async {
+            let response = builder.send().await?;
+            let status = response.status().as_u16();
+            let body = response.text().await?;
+            Ok::<_, Error>(UpstreamResponse { status, body })
+        }
+        .await;
+
+        match &response {
+            Ok(response) => {
+                self.cookies
+                    .report(index, response.status, response.is_anti_crawler());
+            }
+            Err(_) => self.cookies.report_error(index),
+        }
+
+        response
+    }
+}
+
+/// Serves responses from files laid out like the upstream urls, e.g.
+/// `<root>/www.zhihu.com/api/v4/questions/1/feeds.json` or
+/// `<root>/zhuanlan.zhihu.com/p/1.html`. Query strings are ignored.
+pub struct FixtureBackend {
+    root: PathBuf,
+}
+
+impl FixtureBackend {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// `None` for urls that would leave the fixture root, ids come from the client.
+    pub fn path(&self, request: &UpstreamRequest) -> Option<PathBuf> {
+        let path = request
+            .url
+            .split_once("://")
+            .map(|(_, path)| path)
+            .unwrap_or(&request.url);
+        let ext = if request.endpoint.is_page() {
+            "html"
+        } else {
+            "json"
+        };
+
+        let relative = PathBuf::from(format!("{}.{}", path.trim_end_matches('/'), ext));
+        let contained = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        contained.then(|| self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl ZhihuBackend for FixtureBackend {
+    async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+        let Some(path) = self.path(&request) else {
+            warn!("refuse fixture outside the root for {}", request.url);
+            return Ok(UpstreamResponse {
+                status: 404,
+                body: String::new(),
+            });
+        };
+        debug!("read fixture {}", path.display());
+
+        match tokio::fs::read_to_string(&path).await {
+            Ok(body) => Ok(UpstreamResponse { status: 200, body }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(UpstreamResponse {
+                status: 404,
+                body: String::new(),
+            }),
+            Err(err) => Err(anyhow::anyhow!("read fixture {}: {}", path.display(), err).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> FixtureBackend {
+        FixtureBackend::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+    }
+
+    /// Answers `{}` to everything and keeps the requests.
+    #[derive(Default)]
+    struct Recorder {
+        requests: std::sync::Mutex<Vec<UpstreamRequest>>,
+    }
+
+    #[async_trait]
+    impl ZhihuBackend for Recorder {
+        async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+            self.requests.lock().unwrap().push(request);
+            Ok(UpstreamResponse {
+                status: 200,
+                body: "{}".to_string(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn endpoint_requests() {
+        let backend = Recorder::default();
+        let query = json!({ "limit": "5" });
+
+        backend.recommend(&json!({})).await.unwrap();
+        backend.question("1").await.unwrap();
+        backend.question_feeds("1", &query).await.unwrap();
+        backend.answer("1", "2", &json!({})).await.unwrap();
+        backend.article("3").await.unwrap();
+        backend.root_comment("answers", "2", &query).await.unwrap();
+        backend.child_comment("4", &query).await.unwrap();
+        backend.search(&json!({ "q": "rust" })).await.unwrap();
+
+        let requests = backend.requests.lock().unwrap();
+        let requests: Vec<(Endpoint, &str)> = requests
+            .iter()
+            .map(|request| (request.endpoint, request.url.as_str()))
+            .collect();
+        assert_eq!(
+            requests,
+            [
+                (
+                    Endpoint::Recommend,
+                    "https://www.zhihu.com/api/v3/feed/topstory/recommend"
+                ),
+                (Endpoint::Question, "https://www.zhihu.com/question/1"),
+                (
+                    Endpoint::QuestionFeeds,
+                    "https://www.zhihu.com/api/v4/questions/1/feeds"
+                ),
+                (
+                    Endpoint::Answer,
+                    "https://www.zhihu.com/question/1/answer/2"
+                ),
+                (Endpoint::Article, "https://zhuanlan.zhihu.com/p/3"),
+                (
+                    Endpoint::RootComment,
+                    "https://www.zhihu.com/api/v4/comment_v5/answers/2/root_comment"
+                ),
+                (
+                    Endpoint::ChildComment,
+                    "https://www.zhihu.com/api/v4/comment_v5/comment/4/child_comment"
+                ),
+                (Endpoint::Search, "https://www.zhihu.com/api/v4/search_v3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn fixture_paths() {
+        let backend = FixtureBackend::new("fixtures");
+        let path = |endpoint, url: &str| backend.path(&UpstreamRequest::new(endpoint, url));
+
+        assert_eq!(
+            path(
+                Endpoint::Recommend,
+                "https://www.zhihu.com/api/v3/feed/topstory/recommend"
+            ),
+            Some(PathBuf::from(
+                "fixtures/www.zhihu.com/api/v3/feed/topstory/recommend.json"
+            ))
+        );
+        // pages are html, the query never takes part
+        let request = UpstreamRequest::new(Endpoint::Question, "https://www.zhihu.com/question/1/")
+            .query(&json!({ "sort": "created" }));
+        assert_eq!(
+            backend.path(&request),
+            Some(PathBuf::from("fixtures/www.zhihu.com/question/1.html"))
+        );
+
+        // decoded route params can hold `..`
+        assert_eq!(
+            path(
+                Endpoint::Article,
+                "https://zhuanlan.zhihu.com/p/../../../etc/passwd"
+            ),
+            None
+        );
+        assert_eq!(path(Endpoint::Article, "/etc/passwd"), None);
+    }
+
+    #[tokio::test]
+    async fn fixture_endpoints() {
+        let backend = fixtures();
+
+        let feeds = backend
+            .question_feeds("19550225", &json!({ "limit": "5" }))
+            .await
+            .unwrap();
+        assert_eq!(feeds["paging"]["totals"], 42);
+
+        let html = backend.article("2000001").await.unwrap();
+        assert!(html.contains("js-initialData"));
+    }
+
+    #[tokio::test]
+    async fn fixture_missing() {
+        let backend = fixtures();
+        let response = backend
+            .fetch(UpstreamRequest::new(
+                Endpoint::Article,
+                "https://zhuanlan.zhihu.com/p/404",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 404);
+
+        assert!(matches!(backend.article("404").await, Err(Error::NotFound)));
+        // an existing file, but only reachable through `..`
+        assert!(matches!(
+            backend
+                .article("../../www.zhihu.com/question/19550225")
+                .await,
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn response_status() {
+        let response = |status: u16, body: &str| UpstreamResponse {
+            status,
+            body: body.to_string(),
+        };
+
+        assert_eq!(response(200, "{}").text().unwrap(), "{}");
+        assert!(matches!(
+            response(401, "").text(),
+            Err(Error::Unauthorized(401))
+        ));
+        assert!(matches!(response(429, "").text(), Err(Error::RateLimited)));
+        assert!(matches!(
+            response(500, "").text(),
+            Err(Error::Upstream(500))
+        ));
+        assert!(matches!(
+            response(
+                403,
+                r#"{"error":{"code":40362,"message":"您当前请求存在异常"}}"#
+            )
+            .text(),
+            Err(Error::AntiCrawler)
+        ));
+        assert!(matches!(
+            response(
+                200,
+                r#"<a href="https://www.zhihu.com/account/unhuman?type=unhuman">"#
+            )
+            .text(),
+            Err(Error::AntiCrawler)
+        ));
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    backend::{Endpoint, UpstreamRequest, UpstreamResponse, ZhihuBackend},
+    types::Error,
+};
+
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// How long a successful response of each endpoint stays fresh.
+pub fn default_ttl(endpoint: Endpoint) -> Duration {
+    let secs = match endpoint {
+        Endpoint::Recommend => 30,
+        Endpoint::HotList => 60,
+        Endpoint::Search => 5 * 60,
+        Endpoint::Question
+        | Endpoint::QuestionFeeds
+        | Endpoint::MemberItems
+        | Endpoint::ColumnItems => 5 * 60,
+        Endpoint::Answer
+        | Endpoint::Pin
+        | Endpoint::Member
+        | Endpoint::ZVideo
+        | Endpoint::VideoPlaylist => 10 * 60,
+        Endpoint::RootComment | Endpoint::ChildComment => 30 * 60,
+        Endpoint::Article | Endpoint::Column => 60 * 60,
+    };
+    Duration::from_secs(secs)
+}
+
+struct Entry {
+    response: UpstreamResponse,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+/// Keeps successful upstream responses in memory, keyed by url and query.
+///
+/// Once `capacity` is reached expired entries are dropped first, then the least recently used one.
+pub struct CacheBackend {
+    inner: Arc<dyn ZhihuBackend>,
+    capacity: usize,
+    ttls: HashMap<Endpoint, Duration>,
+    entries: Mutex<HashMap<String, Entry>>,
+    clock: AtomicU64,
+}
+
+impl CacheBackend {
+    pub fn new(inner: Arc<dyn ZhihuBackend>, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            ttls: HashMap::new(),
+            entries: Mutex::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_ttl(mut self, endpoint: Endpoint, ttl: Duration) -> Self {
+        self.ttls.insert(endpoint, ttl);
+        self
+    }
+
+    pub fn ttl(&self, endpoint: Endpoint) -> Duration {
+        self.ttls
+            .get(&endpoint)
+            .copied()
+            .unwrap_or_else(|| default_ttl(endpoint))
+    }
+
+    fn get(&self, key: &str) -> Option<UpstreamResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+
+        match entries.get_mut(key) {
+            Some(entry) if entry.expires_at > now => {
+                entry.last_used = self.clock.fetch_add(1, Ordering::Relaxed);
+                Some(entry.response.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: String, response: UpstreamResponse, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        while entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let lru = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match lru {
+                Some(lru) => {
+                    debug!("cache evict {}", lru);
+                    entries.remove(&lru);
+                }
+                None => break,
+            }
+        }
+
+        entries.insert(
+            key,
+            Entry {
+                response,
+                expires_at: now + ttl,
+                last_used: self.clock.fetch_add(1, Ordering::Relaxed),
+            },
+        );
+    }
+}
+
+#[async_trait]
+impl ZhihuBackend for CacheBackend {
+    async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+        let ttl = self.ttl(request.endpoint);
+        if self.capacity == 0 || ttl.is_zero() {
+            return self.inner.fetch(request).await;
+        }
+
+        let key = request.key();
+        if let Some(response) = self.get(&key) {
+            debug!("cache hit {}", key);
+            return Ok(response);
+        }
+        debug!("cache miss {}", key);
+
+        let response = self.inner.fetch(request).await?;
+        // captcha pages come with a 200 too
+        if response.is_success() && !response.is_anti_crawler() {
+            self.insert(key, response.clone(), ttl);
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ZhihuBackend for Counter {
+        async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(UpstreamResponse {
+                status: if request.url.ends_with("404") {
+                    404
+                } else {
+                    200
+                },
+                body: request.key(),
+            })
+        }
+    }
+
+    fn article(aid: &str) -> UpstreamRequest {
+        UpstreamRequest::new(
+            Endpoint::Article,
+            format!("https://zhuanlan.zhihu.com/p/{}", aid),
+        )
+    }
+
+    #[tokio::test]
+    async fn hit_and_skip_failures() {
+        let counter = Arc::new(Counter::default());
+        let cache = CacheBackend::new(counter.clone(), 10);
+
+        cache.fetch(article("1")).await.unwrap();
+        let response = cache.fetch(article("1")).await.unwrap();
+        assert_eq!(response.body, "https://zhuanlan.zhihu.com/p/1");
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 1);
+
+        cache.fetch(article("404")).await.unwrap();
+        cache.fetch(article("404")).await.unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 3);
+
+        let search =
+            UpstreamRequest::new(Endpoint::Search, "https://www.zhihu.com/api/v4/search_v3");
+        cache
+            .fetch(search.clone().query(&json!({ "q": "a" })))
+            .await
+            .unwrap();
+        cache
+            .fetch(search.query(&json!({ "q": "b" })))
+            .await
+            .unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn skip_anti_crawler() {
+        let counter = Arc::new(Counter::default());
+        let cache = CacheBackend::new(counter.clone(), 10);
+
+        // the body echoes the url, so this one reads as a captcha page
+        let captcha = article("account/unhuman");
+        assert!(cache
+            .fetch(captcha.clone())
+            .await
+            .unwrap()
+            .is_anti_crawler());
+        cache.fetch(captcha).await.unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn evict_least_recently_used() {
+        let counter = Arc::new(Counter::default());
+        let cache = CacheBackend::new(counter.clone(), 2);
+
+        cache.fetch(article("1")).await.unwrap();
+        cache.fetch(article("2")).await.unwrap();
+        cache.fetch(article("1")).await.unwrap();
+        cache.fetch(article("3")).await.unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 3);
+
+        // 2 was the least recently used and got evicted
+        cache.fetch(article("1")).await.unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 3);
+        cache.fetch(article("2")).await.unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn expire() {
+        let counter = Arc::new(Counter::default());
+        let cache = CacheBackend::new(counter.clone(), 10)
+            .with_ttl(Endpoint::Article, Duration::ZERO)
+            .with_ttl(Endpoint::Recommend, Duration::from_secs(5));
+        assert_eq!(cache.ttl(Endpoint::Recommend), Duration::from_secs(5));
+
+        cache.fetch(article("1")).await.unwrap();
+        cache.fetch(article("1")).await.unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 2);
+    }
+}
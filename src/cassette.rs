@@ -0,0 +1,179 @@
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::{
+    backend::{UpstreamRequest, UpstreamResponse, ZhihuBackend},
+    types::Error,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteMode {
+    /// Forward to the inner backend and save every exchange.
+    Record,
+    /// Serve saved exchanges only, never touching the network.
+    Replay,
+}
+
+impl FromStr for CassetteMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "record" => Ok(CassetteMode::Record),
+            "replay" => Ok(CassetteMode::Replay),
+            _ => Err(anyhow::anyhow!(
+                "unknown cassette mode {:?}, expected record or replay",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cassette {
+    pub request: UpstreamRequest,
+    pub response: UpstreamResponse,
+}
+
+/// Records upstream request/response pairs into a directory and replays them later,
+/// one json file per distinct url and query.
+pub struct CassetteBackend {
+    inner: Arc<dyn ZhihuBackend>,
+    dir: PathBuf,
+    mode: CassetteMode,
+}
+
+impl CassetteBackend {
+    pub fn new(inner: Arc<dyn ZhihuBackend>, dir: impl Into<PathBuf>, mode: CassetteMode) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+            mode,
+        }
+    }
+
+    pub fn path(&self, request: &UpstreamRequest) -> PathBuf {
+        let key = request.key();
+        let name: String = request
+            .url
+            .split_once("://")
+            .map(|(_, path)| path)
+            .unwrap_or(&request.url)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        self.dir
+            .join(format!("{}-{:016x}.json", name, fnv1a(key.as_bytes())))
+    }
+
+    async fn record(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+        let path = self.path(&request);
+        let response = self.inner.fetch(request.clone()).await?;
+
+        let cassette = Cassette { request, response };
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(anyhow::Error::from)?;
+        tokio::fs::write(&path, serde_json::to_vec_pretty(&cassette)?)
+            .await
+            .map_err(anyhow::Error::from)?;
+        info!("record cassette {}", path.display());
+
+        Ok(cassette.response)
+    }
+
+    async fn replay(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+        let path = self.path(&request);
+        let content = tokio::fs::read(&path).await.map_err(|err| {
+            anyhow::anyhow!(
+                "no cassette for {} at {}: {}",
+                request.key(),
+                path.display(),
+                err
+            )
+        })?;
+        let cassette: Cassette = serde_json::from_slice(&content)?;
+        debug!("replay cassette {}", path.display());
+
+        Ok(cassette.response)
+    }
+}
+
+#[async_trait]
+impl ZhihuBackend for CassetteBackend {
+    async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+        match self.mode {
+            CassetteMode::Record => self.record(request).await,
+            CassetteMode::Replay => self.replay(request).await,
+        }
+    }
+}
+
+/// Stable across builds, unlike `DefaultHasher`, so names derived from it never change.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::backend::Endpoint;
+
+    #[derive(Default)]
+    struct Counter {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ZhihuBackend for Counter {
+        async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(UpstreamResponse {
+                status: 200,
+                body: request.key(),
+            })
+        }
+    }
+
+    fn search(q: &str) -> UpstreamRequest {
+        UpstreamRequest::new(Endpoint::Search, "https://www.zhihu.com/api/v4/search_v3")
+            .query(&json!({ "q": q }))
+    }
+
+    #[tokio::test]
+    async fn record_then_replay() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let counter = Arc::new(Counter::default());
+        let recorder = CassetteBackend::new(counter.clone(), dir.path(), CassetteMode::Record);
+        let recorded = recorder.fetch(search("rust")).await.unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 1);
+        assert!(recorder.path(&search("rust")).exists());
+
+        let counter = Arc::new(Counter::default());
+        let player = CassetteBackend::new(counter.clone(), dir.path(), CassetteMode::Replay);
+        let replayed = player.fetch(search("rust")).await.unwrap();
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            (replayed.status, replayed.body),
+            (recorded.status, recorded.body)
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_missing_cassette() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let counter = Arc::new(Counter::default());
+        let player = CassetteBackend::new(counter.clone(), dir.path(), CassetteMode::Replay);
+        assert!(player.fetch(search("rust")).await.is_err());
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 0);
+    }
+}
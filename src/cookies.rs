@@ -0,0 +1,203 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{cassette, config::CookiesConfig, types::Error};
+
+pub const DEFAULT_COOKIE: &str = "d_c0=AHAYQW4aKRaPTkeCwzmIwGqf-AOazW4-dpM=|1673495514";
+pub const DEFAULT_BENCH: Duration = Duration::from_secs(5 * 60);
+
+struct Slot {
+    cookie: String,
+    success: AtomicU64,
+    failure: AtomicU64,
+    benched_until: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CookieStats {
+    pub label: String,
+    pub success: u64,
+    pub failure: u64,
+    /// Seconds left before a benched cookie is used again.
+    pub benched_for: Option<u64>,
+}
+
+/// Round-robin pool of zhihu cookies, cookies that get throttled are benched for a while.
+pub struct CookiePool {
+    slots: Vec<Slot>,
+    next: AtomicUsize,
+    bench: Duration,
+}
+
+impl CookiePool {
+    pub fn new(cookies: Vec<String>, bench: Duration) -> Self {
+        let mut cookies: Vec<String> = cookies
+            .into_iter()
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty() && !c.starts_with('#'))
+            .collect();
+        if cookies.is_empty() {
+            cookies.push(DEFAULT_COOKIE.to_string());
+        }
+
+        Self {
+            slots: cookies
+                .into_iter()
+                .map(|cookie| Slot {
+                    cookie,
+                    success: AtomicU64::new(0),
+                    failure: AtomicU64::new(0),
+                    benched_until: Mutex::new(None),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+            bench,
+        }
+    }
+
+    /// Cookies from the cookies file (one per line) if configured, the inline list otherwise.
+    pub fn from_config(config: &CookiesConfig) -> Result<Self, Error> {
+        let cookies = match &config.file {
+            Some(path) => read_lines(path)?,
+            None => config.cookies.clone(),
+        };
+        Ok(Self::new(cookies, Duration::from_secs(config.bench)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Next usable cookie, falls back to the one coming off the bench soonest.
+    pub fn pick(&self) -> (usize, &str) {
+        let now = Instant::now();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+
+        let index = (0..self.len())
+            .map(|i| (start + i) % self.len())
+            .find(|&i| {
+                let benched = self.slots[i].benched_until.lock().unwrap();
+                benched.is_none_or(|until| until <= now)
+            })
+            .unwrap_or_else(|| {
+                (0..self.len())
+                    .min_by_key(|&i| *self.slots[i].benched_until.lock().unwrap())
+                    .unwrap_or_default()
+            });
+
+        (index, &self.slots[index].cookie)
+    }
+
+    pub fn report(&self, index: usize, status: u16, anti_crawler: bool) {
+        let slot = &self.slots[index];
+
+        if (200..300).contains(&status) && !anti_crawler {
+            slot.success.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        slot.failure.fetch_add(1, Ordering::Relaxed);
+        if anti_crawler || status == 401 || status == 403 || status == 429 {
+            warn!(
+                "bench cookie {} for {:?} after status {}",
+                label(index, &slot.cookie),
+                self.bench,
+                status
+            );
+            *slot.benched_until.lock().unwrap() = Some(Instant::now() + self.bench);
+        }
+    }
+
+    pub fn report_error(&self, index: usize) {
+        self.slots[index].failure.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> Vec<CookieStats> {
+        let now = Instant::now();
+
+        self.slots
+            .iter()
+            .enumerate()
+            .map(|(index, slot)| CookieStats {
+                label: label(index, &slot.cookie),
+                success: slot.success.load(Ordering::Relaxed),
+                failure: slot.failure.load(Ordering::Relaxed),
+                benched_for: slot
+                    .benched_until
+                    .lock()
+                    .unwrap()
+                    .filter(|until| *until > now)
+                    .map(|until| (until - now).as_secs()),
+            })
+            .collect()
+    }
+}
+
+fn read_lines(path: impl AsRef<Path>) -> Result<Vec<String>, Error> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("read cookies {}: {}", path.display(), err))?;
+    Ok(content.lines().map(String::from).collect())
+}
+
+/// Never show any part of a cookie, a short hash is enough to tell them apart.
+pub fn label(index: usize, cookie: &str) -> String {
+    let hash = cassette::fnv1a(cookie.as_bytes());
+    format!("#{} {:08x}", index + 1, hash as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> CookiePool {
+        CookiePool::new(
+            vec!["a=1".into(), "# comment".into(), "b=2".into(), "".into()],
+            Duration::from_secs(60),
+        )
+    }
+
+    #[test]
+    fn round_robin() {
+        let pool = pool();
+        assert_eq!(pool.len(), 2);
+
+        let picked: Vec<&str> = (0..4).map(|_| pool.pick().1).collect();
+        assert_eq!(picked, ["a=1", "b=2", "a=1", "b=2"]);
+
+        assert_eq!(
+            CookiePool::new(vec![], DEFAULT_BENCH).pick().1,
+            DEFAULT_COOKIE
+        );
+    }
+
+    #[test]
+    fn bench_throttled() {
+        let pool = pool();
+
+        pool.report(0, 200, false);
+        pool.report(0, 429, false);
+        let picked: Vec<&str> = (0..3).map(|_| pool.pick().1).collect();
+        assert_eq!(picked, ["b=2", "b=2", "b=2"]);
+
+        pool.report(1, 200, true);
+        // everything is benched, use the one released first
+        assert_eq!(pool.pick().1, "a=1");
+
+        let stats = pool.stats();
+        assert_eq!((stats[0].success, stats[0].failure), (1, 1));
+        assert_eq!((stats[1].success, stats[1].failure), (0, 1));
+        assert!(stats[0].benched_for.is_some());
+        assert_eq!(
+            stats[0].label,
+            format!("#1 {:08x}", cassette::fnv1a(b"a=1") as u32)
+        );
+        assert!(!stats[0].label.contains("a=1"));
+    }
+}
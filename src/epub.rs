@@ -0,0 +1,846 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{Cursor, Write},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use http::header;
+use lol_html::{element, html_content::ContentType};
+use maud::{Markup, PreEscaped};
+use once_cell::sync::OnceCell;
+use regex::Regex;
+use serde_json::Value;
+use tokio::task::JoinSet;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    images::{Image, ImageClient},
+    parser,
+    routes::{self, AppState},
+    types::{Column, Error, Question, TimelineItem},
+    views,
+};
+
+const DEFAULT_ANSWERS: usize = 20;
+const MAX_ANSWERS: usize = 100;
+const DEFAULT_ARTICLES: usize = 20;
+const MAX_ARTICLES: usize = 100;
+/// Images past this are linked instead of embedded.
+const MAX_IMAGES: usize = 300;
+const CONCURRENT_DOWNLOADS: usize = 8;
+
+const XML_PROLOG: &str = r#"<?xml version="1.0" encoding="utf-8"?>"#;
+const STYLE: &str = "body { line-height: 1.6; }\n\
+img { max-width: 100%; }\n\
+blockquote { margin-left: 0; padding-left: 1em; border-left: 3px solid #ccc; color: #555; }\n\
+pre { white-space: pre-wrap; }\n\
+.author { color: #555; }\n";
+
+static VOID_RE: OnceCell<Regex> = OnceCell::new();
+static ENTITY_RE: OnceCell<Regex> = OnceCell::new();
+
+struct Chapter {
+    title: String,
+    /// Author line shown under the title.
+    byline: Option<String>,
+    /// Xhtml body, already sanitized.
+    body: String,
+}
+
+/// An epub 3 book, with an ncx table of contents for older readers.
+struct Book {
+    id: String,
+    title: String,
+    creators: Vec<String>,
+    chapters: Vec<Chapter>,
+    /// Embedded images by their path inside the book.
+    images: Vec<(String, Image)>,
+}
+
+impl Book {
+    fn render(&self, filename: &str) -> Result<Response, Error> {
+        Ok((
+            [
+                (header::CONTENT_TYPE, "application/epub+zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!(r#"attachment; filename="{}""#, filename),
+                ),
+            ],
+            self.zip()?,
+        )
+            .into_response())
+    }
+
+    fn zip(&self) -> anyhow::Result<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        // readers sniff the first entry, it has to be uncompressed
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+
+        let mut write = |name: &str, content: String| -> anyhow::Result<()> {
+            zip.start_file(name, deflated)?;
+            zip.write_all(content.as_bytes())?;
+            Ok(())
+        };
+        write("META-INF/container.xml", container().into_string())?;
+        write("OEBPS/content.opf", self.opf().into_string())?;
+        write("OEBPS/nav.xhtml", self.nav().into_string())?;
+        write("OEBPS/toc.ncx", self.ncx().into_string())?;
+        write("OEBPS/style.css", STYLE.to_string())?;
+        for (i, chapter) in self.chapters.iter().enumerate() {
+            write(
+                &format!("OEBPS/{}", chapter_href(i)),
+                chapter.xhtml().into_string(),
+            )?;
+        }
+
+        for (path, image) in &self.images {
+            zip.start_file(format!("OEBPS/{}", path), stored)?;
+            zip.write_all(&image.bytes)?;
+        }
+
+        Ok(zip.finish()?.into_inner())
+    }
+
+    fn opf(&self) -> Markup {
+        html! {
+            (PreEscaped(XML_PROLOG))
+            package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id" xml:lang="zh-CN" {
+                metadata xmlns:dc="http://purl.org/dc/elements/1.1/" {
+                    dc:identifier id="id" { (self.id) }
+                    dc:title { (self.title) }
+                    dc:language { "zh-CN" }
+                    @for creator in &self.creators {
+                        dc:creator { (creator) }
+                    }
+                    meta property="dcterms:modified" { (views::rfc3339(Utc::now().timestamp())) }
+                }
+                manifest {
+                    item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav" {}
+                    item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml" {}
+                    item id="style" href="style.css" media-type="text/css" {}
+                    @for i in 0..self.chapters.len() {
+                        item id=(format!("chapter-{}", i + 1)) href=(chapter_href(i)) media-type="application/xhtml+xml" {}
+                    }
+                    @for (i, (path, image)) in self.images.iter().enumerate() {
+                        item id=(format!("image-{}", i + 1)) href=(path) media-type=(image.content_type) {}
+                    }
+                }
+                spine toc="ncx" {
+                    @for i in 0..self.chapters.len() {
+                        itemref idref=(format!("chapter-{}", i + 1)) {}
+                    }
+                }
+            }
+        }
+    }
+
+    fn nav(&self) -> Markup {
+        xhtml(
+            &self.title,
+            html! {
+                nav epub:type="toc" {
+                    h1 { (self.title) }
+                    ol {
+                        @for (i, chapter) in self.chapters.iter().enumerate() {
+                            li { a href=(chapter_href(i)) { (chapter.title) } }
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    fn ncx(&self) -> Markup {
+        html! {
+            (PreEscaped(XML_PROLOG))
+            ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1" {
+                head {
+                    meta name="dtb:uid" content=(self.id) {}
+                }
+                docTitle { text { (self.title) } }
+                navMap {
+                    @for (i, chapter) in self.chapters.iter().enumerate() {
+                        navPoint id=(format!("nav-{}", i + 1)) playOrder=((i + 1).to_string()) {
+                            navLabel { text { (chapter.title) } }
+                            content src=(chapter_href(i)) {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Chapter {
+    fn xhtml(&self) -> Markup {
+        xhtml(
+            &self.title,
+            html! {
+                h1 { (self.title) }
+                @if let Some(byline) = &self.byline {
+                    p.author { (byline) }
+                }
+                (PreEscaped(&self.body))
+            },
+        )
+    }
+}
+
+fn chapter_href(i: usize) -> String {
+    format!("chapter-{}.xhtml", i + 1)
+}
+
+fn container() -> Markup {
+    html! {
+        (PreEscaped(XML_PROLOG))
+        container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container" {
+            rootfiles {
+                rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml" {}
+            }
+        }
+    }
+}
+
+fn xhtml(title: &str, body: Markup) -> Markup {
+    html! {
+        (PreEscaped(XML_PROLOG))
+        (PreEscaped("<!DOCTYPE html>"))
+        html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="zh-CN" lang="zh-CN" {
+            head {
+                title { (title) }
+                link rel="stylesheet" type="text/css" href="style.css" {}
+            }
+            body { (body) }
+        }
+    }
+}
+
+fn image_src(el: &lol_html::html_content::Element) -> String {
+    el.get_attribute("data-original")
+        .or_else(|| el.get_attribute("data-actualsrc"))
+        .or_else(|| el.get_attribute("src"))
+        .unwrap_or_default()
+}
+
+/// Zhihu images referenced by `html`, in order of appearance.
+fn image_urls(html: &str) -> Vec<String> {
+    let urls = RefCell::new(Vec::new());
+    lol_html::rewrite_str(
+        html,
+        lol_html::Settings {
+            element_content_handlers: vec![element!("img:not([eeimg])", |el| {
+                let src = image_src(el);
+                if ImageClient::resolve(&src).is_some() {
+                    urls.borrow_mut().push(src);
+                }
+                Ok(())
+            })],
+            ..Default::default()
+        },
+    )
+    .unwrap_or_default();
+    urls.into_inner()
+}
+
+/// Downloads each distinct url once, failed downloads are left out.
+async fn download(images: &Arc<ImageClient>, urls: Vec<String>) -> HashMap<String, Image> {
+    let mut pending = Vec::new();
+    for url in urls {
+        if pending.len() >= MAX_IMAGES {
+            break;
+        }
+        if !pending.contains(&url) {
+            pending.push(url);
+        }
+    }
+
+    let mut downloaded = HashMap::new();
+    for chunk in pending.chunks(CONCURRENT_DOWNLOADS) {
+        let mut set = JoinSet::new();
+        for url in chunk {
+            let (images, url) = (images.clone(), url.clone());
+            set.spawn(async move { images.fetch(&url).await });
+        }
+        while let Some(result) = set.join_next().await {
+            match result {
+                Ok(Ok(image)) => {
+                    downloaded.insert(image.url.clone(), image);
+                }
+                Ok(Err(err)) => info!("skip image: {}", err),
+                Err(err) => info!("skip image: {}", err),
+            }
+        }
+    }
+    downloaded
+}
+
+/// Turns zhihu content html into xhtml, pointing images at `images`
+/// (original url to path inside the book) and linking the rest.
+/// Links readers may follow, some readers run scripts from `javascript:` urls.
+/// In-book anchors like zhihu's footnotes stay.
+fn safe_href(href: &str) -> bool {
+    if href.starts_with('#') {
+        return true;
+    }
+    reqwest::Url::parse(href).is_ok_and(|url| matches!(url.scheme(), "http" | "https" | "mailto"))
+}
+
+fn sanitize(html: &str, images: &HashMap<String, String>) -> String {
+    const ATTRIBUTES: &[&str] = &["href", "src", "alt", "title", "colspan", "rowspan"];
+
+    let html = lol_html::rewrite_str(
+        html,
+        lol_html::Settings {
+            element_content_handlers: vec![
+                element!("noscript, script, style, iframe, svg", |el| {
+                    el.remove();
+                    Ok(())
+                }),
+                element!("img[eeimg]", |el| {
+                    let tex = el.get_attribute("alt").unwrap_or_default();
+                    let formula = html! { code { (tex.trim()) } };
+                    el.replace(&formula.into_string(), ContentType::Html);
+                    Ok(())
+                }),
+                element!("img", |el| {
+                    if el.removed() {
+                        return Ok(());
+                    }
+                    let src = image_src(el);
+                    let alt = el.get_attribute("alt").unwrap_or_default();
+                    let replacement = match images.get(&src) {
+                        Some(path) => html! { img src=(path) alt=(alt); },
+                        None if !safe_href(&src) => html! {},
+                        None => html! { a href=(src) { "[图片]" } },
+                    };
+                    el.replace(&replacement.into_string(), ContentType::Html);
+                    Ok(())
+                }),
+                element!("a[href]", |el| {
+                    let href = el.get_attribute("href").unwrap_or_default();
+                    let href = match href.split_once("link.zhihu.com/?target=") {
+                        Some((_, target)) => urlencoding::decode(target)
+                            .map(|t| t.into_owned())
+                            .unwrap_or(href),
+                        None if href.starts_with("//") => format!("https:{}", href),
+                        None if href.starts_with('/') => format!("https://www.zhihu.com{}", href),
+                        None => href,
+                    };
+                    if safe_href(&href) {
+                        el.set_attribute("href", &href)?;
+                    } else {
+                        el.remove_attribute("href");
+                    }
+                    Ok(())
+                }),
+                element!("*", |el| {
+                    let names: Vec<String> = el
+                        .attributes()
+                        .iter()
+                        .map(|attr| attr.name())
+                        .filter(|name| !ATTRIBUTES.contains(&name.as_str()))
+                        .collect();
+                    for name in names {
+                        el.remove_attribute(&name);
+                    }
+                    Ok(())
+                }),
+            ],
+            ..Default::default()
+        },
+    )
+    .unwrap_or_default();
+
+    let void_re =
+        VOID_RE.get_or_init(|| Regex::new(r"<(br|hr|img|col|wbr)(\s[^>]*?)?\s*/?>").unwrap());
+    let html = void_re.replace_all(&html, "<$1$2/>");
+
+    // xhtml only knows the xml entities
+    let entity_re = ENTITY_RE.get_or_init(|| Regex::new(r"&([a-zA-Z]+);").unwrap());
+    entity_re
+        .replace_all(&html, |caps: &regex::Captures| match &caps[1] {
+            "amp" | "lt" | "gt" | "quot" | "apos" => caps[0].to_string(),
+            "nbsp" => "&#160;".to_string(),
+            _ => format!("&amp;{};", &caps[1]),
+        })
+        .into_owned()
+}
+
+fn content(item: &TimelineItem) -> &str {
+    item.content
+        .as_ref()
+        .or(item.excerpt.as_ref())
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+fn byline(item: &TimelineItem) -> Option<String> {
+    let author = item.author.as_ref()?;
+    let mut byline = author.name.clone();
+    if !author.headline.is_empty() {
+        byline.push_str(&format!("，{}", author.headline));
+    }
+    byline.push_str(&format!(" · {} 赞同", item.voteup_count));
+    if let Some(created) = item.created_time {
+        byline.push_str(&format!(" · 发布于 {}", views::time(created)));
+    }
+    Some(byline)
+}
+
+/// Downloads the images of `sources` and sanitizes each with the local paths.
+async fn embed(images: &Arc<ImageClient>, sources: &[&str]) -> (Vec<String>, Vec<(String, Image)>) {
+    let urls = sources.iter().flat_map(|html| image_urls(html)).collect();
+    let downloaded = download(images, urls).await;
+
+    let mut paths = HashMap::new();
+    let mut embedded = Vec::new();
+    for (url, image) in downloaded {
+        let path = format!("images/{}.{}", embedded.len() + 1, image.extension());
+        paths.insert(url, path.clone());
+        embedded.push((path, image));
+    }
+
+    let bodies = sources.iter().map(|html| sanitize(html, &paths)).collect();
+    (bodies, embedded)
+}
+
+/// The question detail followed by each answer, `bodies` in the same order.
+fn question_book(
+    question: &Question,
+    answers: &[TimelineItem],
+    bodies: Vec<String>,
+    images: Vec<(String, Image)>,
+) -> Book {
+    let mut bodies = bodies.into_iter();
+
+    let mut chapters = vec![Chapter {
+        title: question.title.clone(),
+        byline: Some(format!(
+            "{} 个回答 · 收录 {} 个",
+            question.answer_count,
+            answers.len()
+        )),
+        body: bodies.next().unwrap_or_default(),
+    }];
+    let mut creators: Vec<String> = Vec::new();
+    for (answer, body) in answers.iter().zip(bodies) {
+        let name = answer
+            .author
+            .as_ref()
+            .map(|a| a.name.clone())
+            .unwrap_or_default();
+        if !name.is_empty() && !creators.contains(&name) {
+            creators.push(name.clone());
+        }
+        chapters.push(Chapter {
+            title: format!("{} 的回答", name),
+            byline: byline(answer),
+            body,
+        });
+    }
+
+    Book {
+        id: format!("urn:zhihu:question:{}", question.id),
+        title: question.title.clone(),
+        creators,
+        chapters,
+        images,
+    }
+}
+
+fn article_book(article: &TimelineItem, body: String, images: Vec<(String, Image)>) -> Book {
+    let title = article.title.clone().unwrap_or_default();
+
+    Book {
+        id: format!("urn:zhihu:article:{}", article.id),
+        title: title.clone(),
+        creators: article.author.iter().map(|a| a.name.clone()).collect(),
+        chapters: vec![Chapter {
+            title,
+            byline: byline(article),
+            body,
+        }],
+        images,
+    }
+}
+
+/// The column introduction followed by each article, `bodies` in the same order.
+fn column_book(
+    column: &Column,
+    articles: &[TimelineItem],
+    bodies: Vec<String>,
+    images: Vec<(String, Image)>,
+) -> Book {
+    let mut bodies = bodies.into_iter();
+
+    let mut summary = format!(
+        "{} 篇文章 · 收录 {} 篇",
+        column.articles_count,
+        articles.len()
+    );
+    if let Some(author) = &column.author {
+        summary = format!("{} · {}", author.name, summary);
+    }
+    let mut chapters = vec![Chapter {
+        title: column.title.clone(),
+        byline: Some(summary),
+        body: bodies.next().unwrap_or_default(),
+    }];
+    let mut creators: Vec<String> = column.author.iter().map(|a| a.name.clone()).collect();
+    for (article, body) in articles.iter().zip(bodies) {
+        if let Some(author) = &article.author {
+            if !author.name.is_empty() && !creators.contains(&author.name) {
+                creators.push(author.name.clone());
+            }
+        }
+        chapters.push(Chapter {
+            title: article.title.clone().unwrap_or_default(),
+            byline: byline(article),
+            body,
+        });
+    }
+
+    Book {
+        id: format!("urn:zhihu:column:{}", column.id),
+        title: column.title.clone(),
+        creators,
+        chapters,
+        images,
+    }
+}
+
+/// The query string of a paging url as the json the backend takes.
+fn query_of(url: &str) -> Value {
+    let query = url.split_once('?').map(|(_, q)| q).unwrap_or_default();
+    let decode = |s: &str| {
+        urlencoding::decode(s)
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| s.to_string())
+    };
+
+    Value::Object(
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (decode(k), json!(decode(v))))
+            .collect(),
+    )
+}
+
+/// The question with up to `limit` answers, following the feed paging.
+async fn load_answers(
+    state: &AppState,
+    qid: &str,
+    limit: usize,
+) -> Result<(Question, Vec<TimelineItem>), Error> {
+    let page = routes::load_question(state, qid, json!({})).await?;
+    let mut answers = page.answers.data;
+    let mut paging = page.answers.paging;
+
+    while answers.len() < limit && paging.is_end == Some(false) {
+        let value = state
+            .backend
+            .question_feeds(qid, &query_of(&paging.next))
+            .await?;
+        let results = parser::parse_timeline(&value);
+
+        // a page without anything new would repeat forever
+        if !push_new(&mut answers, results.data) {
+            break;
+        }
+        paging = results.paging;
+    }
+    answers.truncate(limit);
+
+    Ok((page.question, answers))
+}
+
+/// The column with up to `limit` articles, following the item paging. The listing
+/// only has excerpts, so each article is loaded in full, keeping the excerpt when
+/// that fails.
+async fn load_articles(
+    state: &AppState,
+    id: &str,
+    limit: usize,
+) -> Result<(Column, Vec<TimelineItem>), Error> {
+    let page = routes::load_column(state, id, json!({})).await?;
+    let mut listed = page.articles.data;
+    let mut paging = page.articles.paging;
+
+    while listed.len() < limit && paging.is_end == Some(false) {
+        let value = state
+            .backend
+            .column_items(id, &query_of(&paging.next))
+            .await?;
+        let results = parser::parse_list(&value);
+
+        if !push_new(&mut listed, results.data) {
+            break;
+        }
+        paging = results.paging;
+    }
+    listed.truncate(limit);
+
+    let mut articles = Vec::with_capacity(listed.len());
+    for item in listed {
+        match routes::load_article(state, &item.id).await {
+            Ok(page) => articles.push(page.article),
+            Err(err) => {
+                warn!("keep excerpt of article {}: {}", item.id, err);
+                articles.push(item);
+            }
+        }
+    }
+
+    Ok((page.column, articles))
+}
+
+/// Appends the items not in `items` yet, false when there were none.
+fn push_new(items: &mut Vec<TimelineItem>, more: Vec<TimelineItem>) -> bool {
+    let before = items.len();
+    for item in more {
+        if !items.iter().any(|i| i.id == item.id) {
+            items.push(item);
+        }
+    }
+    items.len() > before
+}
+
+/// `?answers=` picks how many answers to include.
+pub async fn question(
+    state: State<AppState>,
+    qid: Path<(String,)>,
+    query: Query<Value>,
+) -> Result<Response, Error> {
+    let qid = qid.0 .0;
+    let limit = query["answers"]
+        .as_str()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(DEFAULT_ANSWERS)
+        .clamp(1, MAX_ANSWERS);
+
+    let (question, answers) = load_answers(&state, &qid, limit).await?;
+    let mut sources = vec![question.detail.as_str()];
+    sources.extend(answers.iter().map(content));
+    let (bodies, images) = embed(&state.images, &sources).await;
+
+    question_book(&question, &answers, bodies, images).render(&format!("question-{}.epub", qid))
+}
+
+pub async fn article(state: State<AppState>, aid: Path<(String,)>) -> Result<Response, Error> {
+    let aid = aid.0 .0;
+    let page = routes::load_article(&state, &aid).await?;
+
+    let (mut bodies, images) = embed(&state.images, &[content(&page.article)]).await;
+
+    article_book(&page.article, bodies.remove(0), images).render(&format!("article-{}.epub", aid))
+}
+
+/// `?articles=` picks how many articles to include.
+pub async fn column(
+    state: State<AppState>,
+    id: Path<(String,)>,
+    query: Query<Value>,
+) -> Result<Response, Error> {
+    let id = id.0 .0;
+    let limit = query["articles"]
+        .as_str()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(DEFAULT_ARTICLES)
+        .clamp(1, MAX_ARTICLES);
+
+    let (column, articles) = load_articles(&state, &id, limit).await?;
+    let intro = if column.description.is_empty() {
+        &column.intro
+    } else {
+        &column.description
+    };
+    let mut sources = vec![intro.as_str()];
+    sources.extend(articles.iter().map(content));
+    let (bodies, images) = embed(&state.images, &sources).await;
+
+    column_book(&column, &articles, bodies, images).render(&format!("column-{}.epub", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn entry(archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn sanitize_html() {
+        let images = HashMap::from([(
+            "https://pic1.zhimg.com/v2-a_r.jpg".to_string(),
+            "images/1.jpg".to_string(),
+        )]);
+        let html = concat!(
+            r#"<p class="ztext" data-pid="x">a&nbsp;b<br>c</p>"#,
+            r#"<noscript><img src="https://pic1.zhimg.com/v2-a_b.jpg"></noscript>"#,
+            r#"<figure><img src="data:image/svg+xml;utf8,&lt;svg&gt;" data-original="https://pic1.zhimg.com/v2-a_r.jpg" width="10"></figure>"#,
+            r#"<img src="https://pic2.zhimg.com/v2-b.jpg">"#,
+            r#"<img eeimg="1" alt="x^2" src="https://www.zhihu.com/equation?tex=x%5E2">"#,
+            r#"<a href="https://link.zhihu.com/?target=https%3A//example.com/" class="external">x</a>"#,
+            r#"<a href="/question/1">q</a>"#,
+        );
+
+        assert_eq!(
+            sanitize(html, &images),
+            concat!(
+                r#"<p>a&#160;b<br/>c</p>"#,
+                r#"<figure><img src="images/1.jpg" alt=""/></figure>"#,
+                r#"<a href="https://pic2.zhimg.com/v2-b.jpg">[图片]</a>"#,
+                r#"<code>x^2</code>"#,
+                r#"<a href="https://example.com/">x</a>"#,
+                r#"<a href="https://www.zhihu.com/question/1">q</a>"#,
+            )
+        );
+        assert_eq!(
+            image_urls(html),
+            // the noscript fallback is never embedded
+            vec![
+                "https://pic1.zhimg.com/v2-a_r.jpg",
+                "https://pic2.zhimg.com/v2-b.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn drop_unsafe_links() {
+        let html = concat!(
+            r#"<a href="javascript:alert(1)">a</a>"#,
+            r#"<a href=" JavaScript:alert(1)">b</a>"#,
+            r#"<a href="data:text/html,&lt;script&gt;">c</a>"#,
+            r#"<a href="mailto:a@example.com">d</a>"#,
+            r##"<a href="#ref_1">e</a>"##,
+            r#"<img data-original="javascript:alert(1)">"#,
+        );
+
+        assert_eq!(
+            sanitize(html, &HashMap::new()),
+            concat!(
+                "<a>a</a>",
+                "<a>b</a>",
+                "<a>c</a>",
+                r#"<a href="mailto:a@example.com">d</a>"#,
+                r##"<a href="#ref_1">e</a>"##,
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn question_epub() {
+        let state = AppState::fixtures();
+        // the fixture repeats the same page, the walk has to stop anyway
+        let (question, answers) = load_answers(&state, "19550225", 10).await.unwrap();
+        assert_eq!(
+            answers.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(),
+            ["1000001", "1000003"]
+        );
+
+        // no downloads in tests, every image is linked
+        let mut bodies = vec![sanitize(&question.detail, &HashMap::new())];
+        bodies.extend(
+            answers
+                .iter()
+                .map(|a| sanitize(content(a), &HashMap::new())),
+        );
+        let images = vec![(
+            "images/1.png".to_string(),
+            Image {
+                url: String::new(),
+                content_type: "image/png".to_string(),
+                bytes: vec![0x89, b'P', b'N', b'G'],
+            },
+        )];
+        let book = question_book(&question, &answers, bodies, images);
+        let bytes = book.zip().unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+
+        let mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        drop(mimetype);
+
+        let opf = entry(&mut archive, "OEBPS/content.opf");
+        assert!(
+            opf.contains(r#"<dc:identifier id="id">urn:zhihu:question:19550225</dc:identifier>"#)
+        );
+        assert!(opf.contains(r#"href="images/1.png" media-type="image/png""#));
+        assert_eq!(opf.matches("<itemref").count(), 3);
+
+        let nav = entry(&mut archive, "OEBPS/nav.xhtml");
+        let author = answers[0].author.as_ref().unwrap();
+        assert!(nav.contains(&format!(
+            r#"<a href="chapter-2.xhtml">{} 的回答</a>"#,
+            author.name
+        )));
+        assert!(opf.contains(&format!("<dc:creator>{}</dc:creator>", author.name)));
+
+        let chapter = entry(&mut archive, "OEBPS/chapter-2.xhtml");
+        assert!(chapter.starts_with(XML_PROLOG));
+        assert!(chapter.contains(r#"<p class="author">"#));
+    }
+
+    #[tokio::test]
+    async fn column_epub() {
+        let state = AppState::fixtures();
+        let (column, articles) = load_articles(&state, "rust-notes", 10).await.unwrap();
+        assert_eq!(
+            articles.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(),
+            ["2000001", "2000002"]
+        );
+        // the first has an article page, the second only its listing
+        assert!(articles[0].content.is_some());
+        assert!(articles[1].content.is_none());
+
+        let mut sources = vec![column.description.as_str()];
+        sources.extend(articles.iter().map(content));
+        let bodies = sources
+            .iter()
+            .map(|html| sanitize(html, &HashMap::new()))
+            .collect();
+        let book = column_book(&column, &articles, bodies, vec![]);
+        let bytes = book.zip().unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+
+        let opf = entry(&mut archive, "OEBPS/content.opf");
+        assert!(
+            opf.contains(r#"<dc:identifier id="id">urn:zhihu:column:rust-notes</dc:identifier>"#)
+        );
+        assert!(opf.contains("<dc:creator>李四</dc:creator>"));
+        assert_eq!(opf.matches("<itemref").count(), 3);
+
+        let nav = entry(&mut archive, "OEBPS/nav.xhtml");
+        assert!(nav.contains(r#"<a href="chapter-2.xhtml">用 axum 写一个知乎客户端</a>"#));
+    }
+
+    #[test]
+    fn paging_query() {
+        assert_eq!(
+            query_of("https://www.zhihu.com/api/v4/questions/1/feeds?cursor=c2&limit=5&include=data%5B*%5D"),
+            json!({ "cursor": "c2", "limit": "5", "include": "data[*]" })
+        );
+    }
+}
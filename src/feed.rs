@@ -0,0 +1,252 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use http::{header, HeaderMap};
+use maud::{Markup, PreEscaped};
+use serde_json::Value;
+
+use crate::{
+    config,
+    routes::{self, AppState},
+    types::{Error, SearchItem, TimelineItem},
+    views,
+};
+
+/// Atom feed of timeline items, links inside the content are relative to `base`.
+struct Feed<'a> {
+    base: String,
+    /// Path of the html page the feed follows.
+    path: String,
+    title: String,
+    items: Vec<&'a TimelineItem>,
+}
+
+impl Feed<'_> {
+    fn render(&self) -> Response {
+        (
+            [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+            self.xml(),
+        )
+            .into_response()
+    }
+
+    fn xml(&self) -> Markup {
+        let self_href = match self.path.split_once('?') {
+            Some((path, query)) => format!("{}{}/feed.xml?{}", self.base, path, query),
+            None => format!("{}{}/feed.xml", self.base, self.path),
+        };
+        let updated = self
+            .items
+            .iter()
+            .filter_map(|item| updated(item))
+            .max()
+            .unwrap_or_else(|| Utc::now().timestamp());
+
+        html! {
+            (PreEscaped(r#"<?xml version="1.0" encoding="utf-8"?>"#))
+            feed xmlns="http://www.w3.org/2005/Atom" {
+                title { (self.title) }
+                id { (self_href) }
+                link rel="self" type="application/atom+xml" href=(self_href) {}
+                link rel="alternate" type="text/html" href=(format!("{}{}", self.base, self.path)) {}
+                updated { (views::rfc3339(updated)) }
+                @for item in &self.items {
+                    (self.entry(item, updated))
+                }
+            }
+        }
+    }
+
+    /// `feed_updated` stands in for items without any time, atom requires one on every entry.
+    fn entry(&self, item: &TimelineItem, feed_updated: i64) -> Markup {
+        let title = item
+            .title
+            .as_ref()
+            .or_else(|| item.question.as_ref().map(|q| &q.title))
+            .cloned()
+            .unwrap_or_default();
+        let content = item
+            .content
+            .as_ref()
+            .or(item.excerpt.as_ref())
+            .map(String::as_str)
+            .unwrap_or_default();
+        let href = views::href(item);
+
+        html! {
+            entry {
+                title type="html" { (title) }
+                id { (views::zhihu_url(item)) }
+                link rel="alternate" type="text/html" href=(format!("{}{}", self.base, href)) {}
+                updated { (views::rfc3339(updated(item).unwrap_or(feed_updated))) }
+                @if let Some(created) = item.created_time {
+                    published { (views::rfc3339(created)) }
+                }
+                @if let Some(author) = &item.author {
+                    author {
+                        name { (author.name) }
+                        @if let Some(href) = views::people_href(author) {
+                            uri { (format!("{}{}", self.base, href)) }
+                        }
+                    }
+                }
+                // relative links in the rendered html need a base to work in feed readers
+                content type="html" xml:base=(format!("{}/", self.base)) {
+                    (views::render_html(content).0)
+                }
+            }
+        }
+    }
+}
+
+fn updated(item: &TimelineItem) -> Option<i64> {
+    item.updated_time.or(item.created_time)
+}
+
+pub async fn recommend(state: State<AppState>, headers: HeaderMap) -> Result<Response, Error> {
+    let results = routes::load_recommend(&state, json!({})).await?;
+
+    Ok(Feed {
+        base: state.base_url.resolve(&headers),
+        path: "/recommend".to_string(),
+        title: "知乎推荐".to_string(),
+        items: results.data.iter().collect(),
+    }
+    .render())
+}
+
+/// Newest answers first.
+pub async fn question(
+    state: State<AppState>,
+    qid: Path<(String,)>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let qid = qid.0 .0;
+    let page = routes::load_question(&state, &qid, json!({ "order": "updated" })).await?;
+
+    Ok(Feed {
+        base: state.base_url.resolve(&headers),
+        path: format!("/question/{}", qid),
+        title: format!("问题: {}", page.question.title),
+        items: page.answers.data.iter().collect(),
+    }
+    .render())
+}
+
+/// Articles in the order the column lists them.
+pub async fn column(
+    state: State<AppState>,
+    id: Path<(String,)>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let id = id.0 .0;
+    let page = routes::load_column(&state, &id, json!({})).await?;
+
+    Ok(Feed {
+        base: state.base_url.resolve(&headers),
+        path: format!("/column/{}", id),
+        title: format!("专栏: {}", page.column.title),
+        items: page.articles.data.iter().collect(),
+    }
+    .render())
+}
+
+pub async fn search(
+    state: State<AppState>,
+    query: Query<Value>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let mut query = query.0;
+    if !query["type"].is_string() {
+        query["type"] = json!("content");
+        query["limit"] = json!(config::ui().search_limit.to_string());
+    }
+    let q = query["q"].as_str().unwrap_or_default();
+    let results = routes::load_search(&state, &query).await?;
+
+    Ok(Feed {
+        base: state.base_url.resolve(&headers),
+        path: format!("/search?q={}", urlencoding::encode(q)),
+        title: format!("搜索: {}", q),
+        items: results
+            .data
+            .iter()
+            .filter_map(|item| match item {
+                SearchItem::SearchResult(item) => Some(item.as_ref()),
+                _ => None,
+            })
+            .collect(),
+    }
+    .render())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn question_feed() {
+        let state = AppState::fixtures();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, "zhihu.example.com".parse().unwrap());
+
+        let page = routes::load_question(&state, "19550225", json!({}))
+            .await
+            .unwrap();
+        let body = Feed {
+            base: state.base_url.resolve(&headers),
+            path: "/question/19550225".to_string(),
+            title: page.question.title.clone(),
+            items: page.answers.data.iter().collect(),
+        }
+        .xml()
+        .into_string();
+
+        assert!(body.starts_with(r#"<?xml version="1.0" encoding="utf-8"?><feed"#));
+        assert!(body.contains(r#"<id>http://zhihu.example.com/question/19550225/feed.xml</id>"#));
+        // the newest answer
+        let updated = body.split("<updated>").nth(1).unwrap();
+        assert!(updated.starts_with("2023-03-29T14:26:40Z</updated>"));
+        assert!(body.contains("<id>https://www.zhihu.com/question/19550225/answer/1000001</id>"));
+        // rendered html escaped as text, links point at our own pages
+        assert!(body.contains(r#"href=&quot;/question/1&quot;"#));
+    }
+
+    #[tokio::test]
+    async fn column_feed() {
+        let state = AppState::fixtures();
+        let page = routes::load_column(&state, "rust-notes", json!({}))
+            .await
+            .unwrap();
+        let mut items: Vec<&TimelineItem> = page.articles.data.iter().collect();
+        let undated = TimelineItem {
+            id: "2000009".to_string(),
+            type_: "article".to_string(),
+            ..Default::default()
+        };
+        items.push(&undated);
+
+        let body = Feed {
+            base: "http://zhihu.example.com".to_string(),
+            path: "/column/rust-notes".to_string(),
+            title: page.column.title.clone(),
+            items,
+        }
+        .xml()
+        .into_string();
+
+        assert!(body.contains(r#"<id>http://zhihu.example.com/column/rust-notes/feed.xml</id>"#));
+        // every entry has an updated time, falling back to the feed's
+        let entries: Vec<&str> = body.split("<entry>").skip(1).collect();
+        assert_eq!(entries.len(), page.articles.data.len() + 1);
+        assert!(entries.iter().all(|entry| entry.contains("<updated>")));
+        let feed_updated = body.split("<updated>").nth(1).unwrap();
+        let last_updated = entries.last().unwrap().split("<updated>").nth(1).unwrap();
+        assert_eq!(
+            last_updated.split('<').next(),
+            feed_updated.split('<').next()
+        );
+    }
+}
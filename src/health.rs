@@ -0,0 +1,122 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{backend::ZhihuBackend, parser, types::Error};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+static HEALTH: Lazy<RwLock<CookieHealth>> = Lazy::new(Default::default);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HealthState {
+    #[default]
+    Unknown,
+    Valid,
+    /// Zhihu answered but rejected or ignored the cookie.
+    Invalid,
+    /// Zhihu is rate limiting or challenging us, the cookie may still be fine.
+    Throttled,
+    /// Zhihu could not be reached, says nothing about the cookie.
+    Unreachable,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CookieHealth {
+    pub state: HealthState,
+    pub message: String,
+    pub checked_at: Option<i64>,
+}
+
+pub fn current() -> CookieHealth {
+    HEALTH.read().unwrap().clone()
+}
+
+/// Probes the recommend api, a working session always gets a non empty feed.
+pub async fn check(backend: &dyn ZhihuBackend) -> CookieHealth {
+    let (state, message) = match backend.recommend(&json!({})).await {
+        Ok(response) => {
+            let results = parser::parse_timeline(&response);
+            if results.data.is_empty() {
+                (HealthState::Invalid, "推荐内容为空".to_string())
+            } else {
+                (
+                    HealthState::Valid,
+                    format!("推荐返回 {} 条内容", results.data.len()),
+                )
+            }
+        }
+        Err(err @ Error::Unauthorized(_)) => (HealthState::Invalid, err.to_string()),
+        Err(err @ (Error::AntiCrawler | Error::RateLimited)) => {
+            (HealthState::Throttled, err.to_string())
+        }
+        Err(err) => (HealthState::Unreachable, err.to_string()),
+    };
+
+    let health = CookieHealth {
+        state,
+        message,
+        checked_at: Some(chrono::Utc::now().timestamp()),
+    };
+    match health.state {
+        HealthState::Valid => info!("cookie health: {:?}", health),
+        _ => warn!("cookie health: {:?}", health),
+    }
+
+    *HEALTH.write().unwrap() = health.clone();
+    health
+}
+
+/// Checks right away, then every `interval` unless it is zero.
+pub fn spawn(backend: Arc<dyn ZhihuBackend>, interval: Duration) {
+    tokio::spawn(async move {
+        loop {
+            check(backend.as_ref()).await;
+            if interval.is_zero() {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::backend::{FixtureBackend, UpstreamRequest, UpstreamResponse};
+
+    /// Answers every request with the given status.
+    struct Status(u16);
+
+    #[async_trait]
+    impl ZhihuBackend for Status {
+        async fn fetch(&self, _: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+            Ok(UpstreamResponse {
+                status: self.0,
+                body: String::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn check_cookie() {
+        let fixtures = FixtureBackend::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"));
+        let health = check(&fixtures).await;
+        assert_eq!(health.state, HealthState::Valid);
+        assert!(health.checked_at.is_some());
+
+        let health = check(&Status(401)).await;
+        assert_eq!(health.state, HealthState::Invalid);
+    }
+
+    #[tokio::test]
+    async fn throttled_is_not_invalid() {
+        let health = check(&Status(429)).await;
+        assert_eq!(health.state, HealthState::Throttled);
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{self, StreamBody},
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+};
+use http::{HeaderMap, StatusCode};
+use reqwest::{header, Client, Url};
+use serde_json::Value;
+
+use crate::{
+    backend::ClientOptions,
+    resize::{self, Accepted, ResizeCache},
+    routes::AppState,
+    types::Error,
+};
+
+/// Request headers passed on to zhimg.com, so browsers can revalidate.
+const FORWARDED: [header::HeaderName; 2] = [header::IF_NONE_MATCH, header::IF_MODIFIED_SINCE];
+/// Response headers passed back to the browser.
+const PASSED: [header::HeaderName; 6] = [
+    header::CONTENT_TYPE,
+    header::CONTENT_LENGTH,
+    header::CACHE_CONTROL,
+    header::EXPIRES,
+    header::ETAG,
+    header::LAST_MODIFIED,
+];
+/// Used for resized images and when zhimg.com sends no cache headers, images
+/// never change under the same url.
+const DEFAULT_CACHE_CONTROL: &str = "public, max-age=2592000";
+
+pub struct Image {
+    pub url: String,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl Image {
+    /// File extension matching the content type.
+    pub fn extension(&self) -> &'static str {
+        match self.content_type.as_str() {
+            "image/png" => "png",
+            "image/gif" => "gif",
+            "image/webp" => "webp",
+            "image/svg+xml" => "svg",
+            _ => "jpg",
+        }
+    }
+}
+
+/// Downloads images from zhihu's image hosts, nothing else.
+pub struct ImageClient {
+    client: Client,
+    resized: ResizeCache,
+}
+
+impl ImageClient {
+    pub fn new(options: &ClientOptions) -> Result<Self, Error> {
+        Ok(Self {
+            client: options.client()?,
+            resized: ResizeCache::new(resize::DEFAULT_CAPACITY),
+        })
+    }
+
+    /// The absolute url of a zhihu image, `None` for anything hosted elsewhere.
+    pub fn resolve(url: &str) -> Option<Url> {
+        let url = match url.strip_prefix("//") {
+            Some(rest) => Url::parse(&format!("https://{}", rest)),
+            None => Url::parse(url),
+        }
+        .ok()?;
+
+        let host = url.host_str()?;
+        let allowed = matches!(url.scheme(), "http" | "https")
+            && (host == "zhimg.com" || host.ends_with(".zhimg.com"));
+        allowed.then_some(url)
+    }
+
+    async fn send(&self, url: &str, headers: HeaderMap) -> Result<reqwest::Response, Error> {
+        let resolved = Self::resolve(url).ok_or(Error::NotFound)?;
+
+        Ok(self
+            .client
+            .get(resolved)
+            .headers(headers)
+            .header(header::REFERER, "https://www.zhihu.com/")
+            .send()
+            .await?)
+    }
+
+    pub async fn fetch(&self, url: &str) -> Result<Image, Error> {
+        let response = self.send(url, HeaderMap::new()).await?;
+        let status = response.status().as_u16();
+        match status {
+            200..=299 => {}
+            404 | 410 => return Err(Error::NotFound),
+            _ => return Err(Error::Upstream(status)),
+        }
+
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        if !content_type.starts_with("image/") {
+            return Err(Error::Upstream(status));
+        }
+
+        Ok(Image {
+            url: url.to_string(),
+            content_type,
+            bytes: response.bytes().await?.to_vec(),
+        })
+    }
+}
+
+impl ImageClient {
+    /// `url` scaled down to a width preset, in the best format the browser takes.
+    /// Images that can't be resized are returned as they are.
+    pub async fn resized(
+        &self,
+        url: &str,
+        width: u32,
+        accepted: Accepted,
+    ) -> Result<Arc<Image>, Error> {
+        let width = resize::preset(width);
+        if let Some(image) = self.resized.get(url, width, accepted) {
+            return Ok(image);
+        }
+
+        let original = self.fetch(url).await?;
+        let image = if resize::is_resizable(&original) {
+            let format = accepted.format(&original.content_type);
+            tokio::task::spawn_blocking(move || {
+                resize::resize(&original, width, format).unwrap_or_else(|err| {
+                    info!("resize {}: {:?}", original.url, err);
+                    original
+                })
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+        } else {
+            original
+        };
+
+        let image = Arc::new(image);
+        self.resized.insert(width, accepted, image.clone());
+        Ok(image)
+    }
+}
+
+fn is_image(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("image/"))
+}
+
+/// `/img?url=`, streams a zhihu image to the browser. With `&w=` the image is
+/// resized to a width preset and re-encoded for the browser's `Accept` header.
+pub async fn proxy(state: State<AppState>, query: Query<Value>, headers: HeaderMap) -> Response {
+    let url = query["url"].as_str().unwrap_or_default();
+    if ImageClient::resolve(url).is_none() {
+        return (StatusCode::BAD_REQUEST, "not a zhihu image").into_response();
+    }
+
+    if let Some(width) = query["w"].as_str().and_then(|w| w.parse().ok()) {
+        let accept = headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        return match state
+            .images
+            .resized(url, width, Accepted::parse(accept))
+            .await
+        {
+            Ok(image) => (
+                [
+                    (header::CONTENT_TYPE, image.content_type.clone()),
+                    (header::CACHE_CONTROL, DEFAULT_CACHE_CONTROL.to_string()),
+                    (header::VARY, "Accept".to_string()),
+                ],
+                image.bytes.clone(),
+            )
+                .into_response(),
+            Err(Error::NotFound) => StatusCode::NOT_FOUND.into_response(),
+            Err(err) => {
+                error!("image {}: {:?}", url, err);
+                StatusCode::BAD_GATEWAY.into_response()
+            }
+        };
+    }
+
+    let mut forwarded = HeaderMap::new();
+    for name in FORWARDED {
+        if let Some(value) = headers.get(&name) {
+            forwarded.insert(name, value.clone());
+        }
+    }
+
+    let upstream = match state.images.send(url, forwarded).await {
+        Ok(upstream) => upstream,
+        Err(err) => {
+            error!("image {}: {:?}", url, err);
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+    let status = upstream.status();
+    match status {
+        StatusCode::NOT_MODIFIED => {}
+        _ if status.is_success() && is_image(&upstream) => {}
+        StatusCode::NOT_FOUND | StatusCode::GONE => return status.into_response(),
+        _ => {
+            info!("image {}: upstream {}", url, status);
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    }
+
+    let mut response = Response::builder().status(status);
+    for name in PASSED {
+        if let Some(value) = upstream.headers().get(&name) {
+            response = response.header(name, value);
+        }
+    }
+    if !upstream.headers().contains_key(header::CACHE_CONTROL) {
+        response = response.header(header::CACHE_CONTROL, DEFAULT_CACHE_CONTROL);
+    }
+
+    response
+        .body(body::boxed(StreamBody::new(upstream.bytes_stream())))
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_zhimg() {
+        assert_eq!(
+            ImageClient::resolve("//pic1.zhimg.com/v2-a.jpg")
+                .unwrap()
+                .as_str(),
+            "https://pic1.zhimg.com/v2-a.jpg"
+        );
+        assert!(ImageClient::resolve("https://picx.zhimg.com/v2-a.jpg").is_some());
+        assert!(ImageClient::resolve("https://evil.com/?.zhimg.com").is_none());
+        assert!(ImageClient::resolve("https://zhimg.com.evil.com/a.jpg").is_none());
+        assert!(ImageClient::resolve("file:///etc/passwd").is_none());
+        assert!(ImageClient::resolve("data:image/png;base64,AAAA").is_none());
+    }
+
+    #[tokio::test]
+    async fn proxy_rejects_other_hosts() {
+        let response = proxy(
+            State(AppState::fixtures()),
+            Query(json!({ "url": "http://127.0.0.1/secret.png" })),
+            HeaderMap::new(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
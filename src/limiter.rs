@@ -0,0 +1,195 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    backend::{Endpoint, UpstreamRequest, UpstreamResponse, ZhihuBackend},
+    types::Error,
+};
+
+pub const DEFAULT_RATE: f64 = 5.0;
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(3);
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token bucket refilled at `rate` tokens per second, holding at most `burst`.
+///
+/// Tokens may go negative, which is how waiting requests queue up behind each other.
+/// A rate of zero or less means no limit.
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, burst: f64) -> Self {
+        let burst = burst.max(1.0);
+        Self {
+            rate,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                updated_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token and returns how long to wait before using it,
+    /// or `None` without taking anything if that is longer than `max_wait`.
+    pub fn reserve(&self, max_wait: Duration) -> Option<Duration> {
+        if self.rate <= 0.0 {
+            return Some(Duration::ZERO);
+        }
+
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated_at = now;
+
+        let wait = if bucket.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)
+        };
+        if wait > max_wait {
+            return None;
+        }
+
+        bucket.tokens -= 1.0;
+        Some(wait)
+    }
+
+    /// Gives back a token taken by `reserve` that ended up unused.
+    pub fn refund(&self) {
+        if self.rate > 0.0 {
+            self.bucket.lock().unwrap().tokens += 1.0;
+        }
+    }
+}
+
+/// Applies a global limit and optional per endpoint limits to every upstream request.
+pub struct RateLimitBackend {
+    inner: Arc<dyn ZhihuBackend>,
+    global: TokenBucket,
+    endpoints: HashMap<Endpoint, TokenBucket>,
+    max_wait: Duration,
+}
+
+impl RateLimitBackend {
+    pub fn new(inner: Arc<dyn ZhihuBackend>, rate: f64, burst: f64, max_wait: Duration) -> Self {
+        Self {
+            inner,
+            global: TokenBucket::new(rate, burst),
+            endpoints: HashMap::new(),
+            max_wait,
+        }
+    }
+
+    pub fn with_endpoint_rate(mut self, endpoint: Endpoint, rate: f64) -> Self {
+        self.endpoints
+            .insert(endpoint, TokenBucket::new(rate, rate.ceil()));
+        self
+    }
+}
+
+#[async_trait]
+impl ZhihuBackend for RateLimitBackend {
+    async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+        // the endpoint first, a busy endpoint must not use up the budget of the others
+        let endpoint = self.endpoints.get(&request.endpoint);
+        let endpoint_wait = match endpoint {
+            Some(bucket) => bucket.reserve(self.max_wait).ok_or(Error::Busy)?,
+            None => Duration::ZERO,
+        };
+        let Some(wait) = self.global.reserve(self.max_wait) else {
+            if let Some(bucket) = endpoint {
+                bucket.refund();
+            }
+            return Err(Error::Busy);
+        };
+        let wait = wait.max(endpoint_wait);
+
+        if !wait.is_zero() {
+            debug!("rate limit {} for {:?}", request.url, wait);
+            tokio::time::sleep(wait).await;
+        }
+
+        self.inner.fetch(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_queue() {
+        let bucket = TokenBucket::new(10.0, 2.0);
+        let max_wait = Duration::from_millis(250);
+
+        assert_eq!(bucket.reserve(max_wait), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(max_wait), Some(Duration::ZERO));
+
+        let wait = bucket.reserve(max_wait).unwrap();
+        assert!(wait > Duration::from_millis(50) && wait <= Duration::from_millis(100));
+        let wait = bucket.reserve(max_wait).unwrap();
+        assert!(wait > Duration::from_millis(150) && wait <= Duration::from_millis(200));
+
+        // the next one would wait about 300ms
+        assert_eq!(bucket.reserve(max_wait), None);
+    }
+
+    #[tokio::test]
+    async fn busy() {
+        let fixtures = Arc::new(crate::backend::FixtureBackend::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures"
+        )));
+        let backend = RateLimitBackend::new(fixtures, 100.0, 100.0, Duration::ZERO)
+            .with_endpoint_rate(Endpoint::Recommend, 1.0);
+
+        assert!(backend.recommend(&json!({})).await.is_ok());
+        assert!(matches!(
+            backend.recommend(&json!({})).await,
+            Err(Error::Busy)
+        ));
+        assert!(backend.article("2000001").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn busy_endpoint_keeps_global_tokens() {
+        let fixtures = Arc::new(crate::backend::FixtureBackend::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures"
+        )));
+        let backend = RateLimitBackend::new(fixtures, 0.001, 2.0, Duration::ZERO)
+            .with_endpoint_rate(Endpoint::Recommend, 0.001);
+
+        assert!(backend.recommend(&json!({})).await.is_ok());
+        for _ in 0..3 {
+            assert!(matches!(
+                backend.recommend(&json!({})).await,
+                Err(Error::Busy)
+            ));
+        }
+        // the second global token is still there
+        assert!(backend.article("2000001").await.is_ok());
+        assert!(matches!(backend.article("2000001").await, Err(Error::Busy)));
+
+        // and a refused global token gives the endpoint's back
+        let bucket = TokenBucket::new(0.001, 1.0);
+        assert!(bucket.reserve(Duration::ZERO).is_some());
+        bucket.refund();
+        assert!(bucket.reserve(Duration::ZERO).is_some());
+    }
+}
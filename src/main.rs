@@ -1,3 +1,5 @@
+use std::{sync::Arc, time::Duration};
+
 use axum::{routing::get, Router};
 
 #[macro_use]
@@ -9,34 +11,159 @@ extern crate serde;
 #[macro_use]
 extern crate serde_json;
 
+mod api;
+mod archive;
+mod assets;
+mod backend;
+mod cache;
+mod cassette;
+mod config;
+mod cookies;
+mod epub;
+mod feed;
+mod health;
+mod images;
+mod limiter;
+mod markdown;
 mod parser;
+mod proxy;
+mod resize;
+mod retry;
 mod routes;
 mod types;
 mod views;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+    let args = config::Args::parse(std::env::args().skip(1))?;
+    let config = config::Config::load(&args)?;
+    if args.print_config {
+        print!("{}", config.to_toml()?);
+        return Ok(());
+    }
 
-    let address = std::env::var("ADDRESS").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
+    tracing_subscriber::fmt::init();
 
-    let bind = format!("{}:{}", address, port);
+    let bind = config.bind()?;
     info!("listen on http://{}", bind);
 
+    let cookies = Arc::new(cookies::CookiePool::from_config(&config.cookies)?);
+    info!("use {} cookies", cookies.len());
+
+    let upstream = &config.upstream;
+    let backend: Arc<dyn backend::ZhihuBackend> = match &upstream.fixtures {
+        Some(dir) => {
+            info!("serve fixtures from {}", dir.display());
+            Arc::new(backend::FixtureBackend::new(dir))
+        }
+        None => {
+            let client = Arc::new(backend::ReqwestBackend::new(
+                cookies.clone(),
+                &upstream.client_options(&config.proxy),
+            )?);
+            let rate_limit = &config.rate_limit;
+            let mut limited = limiter::RateLimitBackend::new(
+                client,
+                rate_limit.rate,
+                rate_limit.burst.unwrap_or(rate_limit.rate),
+                Duration::from_millis(rate_limit.wait),
+            );
+            for (&endpoint, &rate) in &rate_limit.endpoints {
+                limited = limited.with_endpoint_rate(endpoint, rate);
+            }
+            Arc::new(retry::RetryBackend::new(
+                Arc::new(limited),
+                upstream.retries,
+                upstream.retry_backoff(),
+            ))
+        }
+    };
+    let backend: Arc<dyn backend::ZhihuBackend> = match upstream.cassette {
+        Some(mode) => {
+            let dir = &upstream.cassette_dir;
+            info!("{:?} cassettes in {}", mode, dir.display());
+            Arc::new(cassette::CassetteBackend::new(backend, dir, mode))
+        }
+        None => backend,
+    };
+    if config.features.health_check {
+        health::spawn(backend.clone(), Duration::from_secs(config.health.interval));
+    }
+
+    let backend: Arc<dyn backend::ZhihuBackend> = if config.features.cache {
+        let mut cache = cache::CacheBackend::new(backend, config.cache.size);
+        for (&endpoint, &secs) in &config.cache.ttl {
+            cache = cache.with_ttl(endpoint, Duration::from_secs(secs));
+        }
+        Arc::new(cache)
+    } else {
+        backend
+    };
+    let archive = if config.features.archive {
+        archive::Archive::open(&config.archive.path)?
+    } else {
+        archive::Archive::disabled()
+    };
+    config::init_ui(config.ui.clone());
+    let images = images::ImageClient::new(&upstream.client_options(&config.proxy))?;
+    let state = routes::AppState {
+        backend,
+        cookies,
+        archive: Arc::new(archive),
+        images: Arc::new(images),
+        base_url: config.base_url(),
+    };
+
     let app = Router::new()
         .route("/", get(routes::index))
+        .route("/static/:file", get(assets::serve))
+        .route("/img", get(images::proxy))
         .route("/recommend", get(routes::recommend))
+        .route("/recommend/feed.xml", get(feed::recommend))
+        .route("/hot", get(routes::hot))
         .route("/question/:qid", get(routes::question))
+        .route("/question/:qid/feed.xml", get(feed::question))
+        .route("/question/:qid/export.md", get(markdown::question_answers))
+        .route("/question/:qid/export.epub", get(epub::question))
         .route("/question/:qid/answer/:aid", get(routes::answer))
+        .route(
+            "/question/:qid/answer/:aid/export.md",
+            get(markdown::answer),
+        )
         .route("/p/:aid", get(routes::article))
+        .route("/p/:aid/export.md", get(markdown::article))
+        .route("/p/:aid/export.epub", get(epub::article))
+        .route("/pin/:id", get(routes::pin))
+        .route("/zvideo/:id", get(routes::zvideo))
+        .route("/column/:id", get(routes::column))
+        .route("/column/:id/feed.xml", get(feed::column))
+        .route("/column/:id/export.epub", get(epub::column))
+        .route("/people/:url_token", get(routes::people))
+        .route("/people/:url_token/:tab", get(routes::people_tab))
         .route("/comment/root/:aid", get(routes::root_comment))
         .route("/comment/child/:cid", get(routes::child_comment))
         .route("/search", get(routes::search))
+        .route("/search/feed.xml", get(feed::search))
+        .route("/status", get(routes::status))
+        .route("/api/recommend", get(api::recommend))
+        .route("/api/hot", get(api::hot))
+        .route("/api/question/:qid", get(api::question))
+        .route("/api/question/:qid/answer/:aid", get(api::answer))
+        .route("/api/p/:aid", get(api::article))
+        .route("/api/pin/:id", get(api::pin))
+        .route("/api/zvideo/:id", get(api::zvideo))
+        .route("/api/column/:id", get(api::column))
+        .route("/api/people/:url_token", get(api::people))
+        .route("/api/people/:url_token/:tab", get(api::people_tab))
+        .route("/api/comment/root/:aid", get(api::root_comment))
+        .route("/api/comment/child/:cid", get(api::child_comment))
+        .route("/api/search", get(api::search))
         .fallback(routes::default)
-        .layer(tower_http::trace::TraceLayer::new_for_http());
+        .layer(axum::middleware::from_fn(routes::no_js))
+        .layer(tower_http::trace::TraceLayer::new_for_http())
+        .with_state(state);
 
-    axum::Server::bind(&bind.parse()?)
+    axum::Server::bind(&bind)
         .serve(app.into_make_service())
         .await?;
 
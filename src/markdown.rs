@@ -0,0 +1,391 @@
+use std::{cell::Cell, rc::Rc};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use http::header;
+use lol_html::{element, html_content::ContentType};
+use once_cell::sync::OnceCell;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    routes::{self, AppState},
+    types::{Error, Question, TimelineItem},
+    views,
+};
+
+static TAG_RE: OnceCell<Regex> = OnceCell::new();
+static ENTITY_RE: OnceCell<Regex> = OnceCell::new();
+static BLANK_RE: OnceCell<Regex> = OnceCell::new();
+
+// blockquotes are marked while rewriting and prefixed with `>` afterwards
+const QUOTE_START: char = '\u{2}';
+const QUOTE_END: char = '\u{3}';
+
+macro_rules! wrap {
+    ($selector:expr, $before:expr, $after:expr) => {
+        element!($selector, |el| {
+            if !el.removed() {
+                el.before($before, ContentType::Text);
+                el.after($after, ContentType::Text);
+            }
+            Ok(())
+        })
+    };
+}
+
+/// Converts zhihu content html into markdown.
+pub fn from_html(html: &str) -> String {
+    let html = views::replace_emoji(html, |name, src| {
+        format!(r#"<img alt="{}" src="{}">"#, name, src)
+    });
+    // rows seen in the current table and cells in the current row, the first row
+    // becomes the header of a pipe table
+    let table = Rc::new(Cell::new((0usize, 0usize)));
+    let (rows, cells) = (table.clone(), table.clone());
+    let markdown = lol_html::rewrite_str(
+        &html,
+        lol_html::Settings {
+            element_content_handlers: vec![
+                element!("noscript, script, style", |el| {
+                    el.remove();
+                    Ok(())
+                }),
+                element!("img[eeimg]", |el| {
+                    let tex = el.get_attribute("alt").unwrap_or_default();
+                    let tex = tex.trim();
+                    // zhihu marks display formulas with a trailing `\\`
+                    let formula = match tex.strip_suffix("\\\\") {
+                        Some(tex) => format!("\n\n$$\n{}\n$$\n\n", tex.trim()),
+                        None => format!("${}$", tex),
+                    };
+                    el.replace(&formula, ContentType::Text);
+                    Ok(())
+                }),
+                element!("img", |el| {
+                    if el.removed() {
+                        return Ok(());
+                    }
+                    let src = el
+                        .get_attribute("data-original")
+                        .or_else(|| el.get_attribute("data-actualsrc"))
+                        .or_else(|| el.get_attribute("src"))
+                        .unwrap_or_default();
+                    if src.starts_with("data:") {
+                        el.remove();
+                        return Ok(());
+                    }
+                    let alt = el.get_attribute("alt").unwrap_or_default();
+                    el.replace(&format!("![{}]({})", alt, src), ContentType::Text);
+                    Ok(())
+                }),
+                element!("br", |el| {
+                    el.replace("  \n", ContentType::Text);
+                    Ok(())
+                }),
+                element!("hr", |el| {
+                    el.replace("\n\n---\n\n", ContentType::Text);
+                    Ok(())
+                }),
+                element!("h1, h2, h3, h4, h5, h6", |el| {
+                    let level: usize = el.tag_name()[1..].parse().unwrap_or(2);
+                    el.before(&format!("\n\n{} ", "#".repeat(level)), ContentType::Text);
+                    el.after("\n\n", ContentType::Text);
+                    Ok(())
+                }),
+                wrap!("p, figure, ul, ol, table", "\n\n", "\n\n"),
+                wrap!("figcaption", "\n*", "*"),
+                wrap!("ul > li", "\n- ", ""),
+                wrap!("ol > li", "\n1. ", ""),
+                element!("table", move |_| {
+                    table.set((0, 0));
+                    Ok(())
+                }),
+                element!("tr", move |el| {
+                    let (row, _) = rows.get();
+                    rows.set((row + 1, 0));
+                    el.before("\n", ContentType::Text);
+
+                    let rows = rows.clone();
+                    el.on_end_tag(move |end| {
+                        let (row, cells) = rows.get();
+                        let mut after = String::from("|");
+                        if row == 1 {
+                            after.push_str(&format!("\n|{}", " --- |".repeat(cells)));
+                        }
+                        end.after(&after, ContentType::Text);
+                        Ok(())
+                    })?;
+                    Ok(())
+                }),
+                element!("td, th", move |el| {
+                    let (row, cell) = cells.get();
+                    cells.set((row, cell + 1));
+                    el.before("| ", ContentType::Text);
+                    el.after(" ", ContentType::Text);
+                    Ok(())
+                }),
+                wrap!("b, strong", "**", "**"),
+                wrap!("i, em", "*", "*"),
+                element!("blockquote", |el| {
+                    el.before(&format!("\n\n{}", QUOTE_START), ContentType::Text);
+                    el.after(&format!("{}\n\n", QUOTE_END), ContentType::Text);
+                    Ok(())
+                }),
+                wrap!("pre", "\n\n", "\n\n"),
+                element!("pre > code", |el| {
+                    let class = el.get_attribute("class").unwrap_or_default();
+                    let lang = class
+                        .split_whitespace()
+                        .find_map(|c| c.strip_prefix("language-"))
+                        .unwrap_or_default();
+                    el.before(&format!("```{}\n", lang), ContentType::Text);
+                    el.after("\n```", ContentType::Text);
+                    Ok(())
+                }),
+                wrap!(":not(pre) > code", "`", "`"),
+                element!("a[href]", |el| {
+                    let href = el.get_attribute("href").unwrap_or_default();
+                    let href = match href.split_once("link.zhihu.com/?target=") {
+                        Some((_, target)) => urlencoding::decode(target)
+                            .map(|t| t.into_owned())
+                            .unwrap_or(href),
+                        None => href,
+                    };
+                    el.prepend("[", ContentType::Text);
+                    el.append(&format!("]({})", href), ContentType::Text);
+                    Ok(())
+                }),
+            ],
+            ..Default::default()
+        },
+    )
+    .unwrap_or_default();
+
+    let tag_re = TAG_RE.get_or_init(|| Regex::new(r"<[^>]*>").unwrap());
+    let markdown = tag_re.replace_all(&markdown, "");
+    let markdown = unescape(&markdown);
+
+    tidy(&quote(&markdown))
+}
+
+/// Prefixes every line between the quote markers with `>`.
+fn quote(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+
+    while let Some(start) = rest.find(QUOTE_START) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + QUOTE_START.len_utf8()..];
+        let end = rest.find(QUOTE_END).unwrap_or(rest.len());
+        let quoted: Vec<String> = rest[..end]
+            .trim()
+            .lines()
+            .map(|line| format!("> {}", line).trim_end().to_string())
+            .collect();
+        out.push_str(&quoted.join("\n"));
+        rest = rest.get(end + QUOTE_END.len_utf8()..).unwrap_or_default();
+    }
+    out.push_str(rest);
+    out
+}
+
+fn tidy(markdown: &str) -> String {
+    let blank_re = BLANK_RE.get_or_init(|| Regex::new(r"\n[ \t]*\n(\s*\n)+").unwrap());
+    blank_re.replace_all(markdown.trim(), "\n\n").into_owned()
+}
+
+fn unescape(text: &str) -> String {
+    let entity_re = ENTITY_RE.get_or_init(|| Regex::new(r"&(#x?[0-9a-fA-F]+|[a-z]+);").unwrap());
+
+    entity_re
+        .replace_all(text, |caps: &regex::Captures| {
+            let entity = &caps[1];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            c.map(String::from).unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Yaml front matter, json strings are valid yaml.
+fn front_matter(fields: &[(&str, Value)]) -> String {
+    let mut out = String::from("---\n");
+    for (key, value) in fields {
+        if !value.is_null() {
+            out.push_str(&format!("{}: {}\n", key, value));
+        }
+    }
+    out.push_str("---\n");
+    out
+}
+
+fn item_fields(item: &TimelineItem) -> Vec<(&'static str, Value)> {
+    vec![
+        ("author", json!(item.author.as_ref().map(|a| &a.name))),
+        ("voteup_count", json!(item.voteup_count)),
+        ("created", json!(item.created_time.map(views::rfc3339))),
+        ("updated", json!(item.updated_time.map(views::rfc3339))),
+        ("source", json!(views::zhihu_url(item))),
+    ]
+}
+
+fn content(item: &TimelineItem) -> String {
+    from_html(
+        item.content
+            .as_ref()
+            .or(item.excerpt.as_ref())
+            .map(String::as_str)
+            .unwrap_or_default(),
+    )
+}
+
+/// A single answer or article with its front matter.
+pub fn item(item: &TimelineItem) -> String {
+    let title = item
+        .title
+        .as_ref()
+        .or_else(|| item.question.as_ref().map(|q| &q.title))
+        .cloned()
+        .unwrap_or_default();
+
+    let mut fields = vec![("title", json!(title))];
+    fields.extend(item_fields(item));
+
+    format!("{}\n{}\n", front_matter(&fields), content(item))
+}
+
+/// The question followed by each of `answers`.
+pub fn question(question: &Question, answers: &[TimelineItem]) -> String {
+    let mut out = front_matter(&[
+        ("title", json!(question.title)),
+        ("answer_count", json!(question.answer_count)),
+        (
+            "source",
+            json!(format!("https://www.zhihu.com/question/{}", question.id)),
+        ),
+    ]);
+
+    let detail = from_html(&question.detail);
+    if !detail.is_empty() {
+        out.push_str(&format!("\n{}\n", detail));
+    }
+
+    for answer in answers {
+        let author = answer.author.as_ref().map(|a| a.name.as_str());
+        out.push_str(&format!("\n## {}\n\n", author.unwrap_or("匿名用户")));
+        let mut meta = vec![format!("{} 赞同", answer.voteup_count)];
+        if let Some(created) = answer.created_time {
+            meta.push(format!("发布于 {}", views::datetime(created)));
+        }
+        if let Some(updated) = answer
+            .updated_time
+            .filter(|&t| Some(t) != answer.created_time)
+        {
+            meta.push(format!("编辑于 {}", views::datetime(updated)));
+        }
+        meta.push(format!("[原文]({})", views::zhihu_url(answer)));
+        out.push_str(&format!("*{}*\n\n{}\n", meta.join(", "), content(answer)));
+    }
+
+    out
+}
+
+fn respond(markdown: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        markdown,
+    )
+        .into_response()
+}
+
+pub async fn answer(
+    state: State<AppState>,
+    p: Path<(String, String)>,
+    query: Query<Value>,
+) -> Result<Response, Error> {
+    let (qid, aid) = p.0;
+    let page = routes::load_answer(&state, &qid, &aid, query.0).await?;
+
+    Ok(respond(item(&page.answer)))
+}
+
+pub async fn article(state: State<AppState>, aid: Path<(String,)>) -> Result<Response, Error> {
+    let page = routes::load_article(&state, &aid.0 .0).await?;
+
+    Ok(respond(item(&page.article)))
+}
+
+/// All answers loaded by the question page with the same query.
+pub async fn question_answers(
+    state: State<AppState>,
+    qid: Path<(String,)>,
+    query: Query<Value>,
+) -> Result<Response, Error> {
+    let page = routes::load_question(&state, &qid.0 .0, query.0).await?;
+
+    Ok(respond(question(&page.question, &page.answers.data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        self,
+        tests::{fixture, fixture_json},
+    };
+
+    #[test]
+    fn convert_html() {
+        let html = concat!(
+            r#"<h2>标题</h2><p>一段<b>加粗</b>和<code>code</code>，公式 <img eeimg="1" src="https://www.zhihu.com/equation?tex=x%5E2" alt="x^2"/>。[赞同]</p>"#,
+            r#"<p><img eeimg="1" alt="\sum_i x_i \\"/></p>"#,
+            r#"<blockquote>第一行<br/>第二行</blockquote>"#,
+            r#"<ul><li>一</li><li>二</li></ul>"#,
+            r#"<div class="highlight"><pre><code class="language-rust">fn main() {&lt;T&gt;}</code></pre></div>"#,
+            r#"<p><a href="https://link.zhihu.com/?target=https%3A//example.com/%3Fa%3D1">链接</a></p>"#,
+            r#"<table data-draft-node="block"><tbody><tr><th>名称</th><th>版本</th></tr><tr><td>axum</td><td>0.6</td></tr><tr><td>maud</td><td>0.25</td></tr></tbody></table>"#,
+        );
+
+        assert_eq!(
+            from_html(html),
+            concat!(
+                "## 标题\n\n",
+                "一段**加粗**和`code`，公式 $x^2$。![赞同](https://pic2.zhimg.com/v2-419a1a3ed02b7cfadc20af558aabc897.png)\n\n",
+                "$$\n\\sum_i x_i\n$$\n\n",
+                "> 第一行\n> 第二行\n\n",
+                "- 一\n- 二\n\n",
+                "```rust\nfn main() {<T>}\n```\n\n",
+                "[链接](https://example.com/?a=1)\n\n",
+                "| 名称 | 版本 |\n| --- | --- |\n| axum | 0.6 |\n| maud | 0.25 |",
+            )
+        );
+    }
+
+    #[test]
+    fn export_answer_and_article() {
+        let results = parser::parse_timeline(&fixture_json(
+            "www.zhihu.com/api/v4/questions/19550225/feeds.json",
+        ));
+        insta::assert_snapshot!(item(&results.data[0]));
+
+        let html = fixture("zhuanlan.zhihu.com/p/2000001.html");
+        let article: TimelineItem =
+            parser::parse_entity_data(&html, "articles", "2000001").unwrap();
+        insta::assert_snapshot!(item(&article));
+    }
+}
@@ -1,38 +1,193 @@
+use maud::{Markup, PreEscaped};
 use once_cell::sync::OnceCell;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
-use crate::types::{ApiResults, SearchItem, TimelineItem};
+use crate::types::{
+    ApiResults, Attachment, AttachmentVideo, HotItem, Pin, PinContent, Question, SearchItem,
+    TimelineItem, VideoInfo, ZVideo,
+};
 
 static JS_INIITAL_DATA_RE: OnceCell<regex::Regex> = OnceCell::new();
 
 pub fn parse_timeline(value: &Value) -> ApiResults<TimelineItem> {
-    let mut results = ApiResults::default();
+    let mut results = ApiResults {
+        paging: serde_json::from_value(value["paging"].clone()).unwrap_or_default(),
+        ..Default::default()
+    };
 
-    results.paging = serde_json::from_value(value["paging"].clone()).unwrap_or_default();
+    let data = value["data"].as_array();
+    if let Some(data) = data {
+        results
+            .data
+            .extend(data.iter().filter_map(|item| parse_item(&item["target"])));
+    }
+
+    results
+}
+
+/// Answers, articles, pins and videos of timelines and search results, other types are dropped.
+fn parse_item(value: &Value) -> Option<TimelineItem> {
+    let str = value.to_string();
+    let jd = &mut serde_json::Deserializer::from_str(&str);
+
+    let object = match value["type"].as_str() {
+        Some("answer" | "article") => serde_path_to_error::deserialize(jd),
+        Some("pin") => serde_path_to_error::deserialize(jd).map(pin_item),
+        Some("zvideo") => serde_path_to_error::deserialize(jd).map(zvideo_item),
+        ty => {
+            debug!("find unsupport type: {}", ty.unwrap_or_default());
+            return None;
+        }
+    };
+
+    match object {
+        Ok(object) => Some(object),
+        Err(err) => {
+            error!("parse timteline item error: {:?}", err);
+            None
+        }
+    }
+}
+
+/// A pin as a timeline item, its text and images become the `content`.
+pub fn pin_item(pin: Pin) -> TimelineItem {
+    let images = pin.images();
+    let content = html! {
+        (PreEscaped(pin.text()))
+        (pin_images(&pin))
+        @if let Some(origin) = &pin.origin_pin {
+            blockquote {
+                @if let Some(author) = &origin.author {
+                    p { "@" (author.name) ":" }
+                }
+                (PreEscaped(origin.text()))
+                (pin_images(origin))
+            }
+        }
+    };
+
+    TimelineItem {
+        id: pin.id.clone(),
+        type_: "pin".to_string(),
+        url: format!("https://www.zhihu.com/pin/{}", pin.id),
+        thumbnail: images.first().map(|url| url.to_string()),
+        content: Some(content.into_string()),
+        author: pin.author.clone(),
+        created_time: pin.created,
+        updated_time: pin.updated,
+        voteup_count: pin.likes(),
+        comment_count: pin.comment_count,
+        ext: json!({}),
+        pin: Some(Box::new(pin)),
+        ..Default::default()
+    }
+}
+
+/// A video as a timeline item, played through its `attachment` like video answers.
+pub fn zvideo_item(zvideo: ZVideo) -> TimelineItem {
+    let thumbnail = [&zvideo.image_url, &zvideo.video.thumbnail]
+        .into_iter()
+        .find(|url| !url.is_empty())
+        .cloned();
+    let description = html! { p { (zvideo.description) } }.into_string();
+
+    TimelineItem {
+        id: zvideo.id.clone(),
+        type_: "zvideo".to_string(),
+        url: format!("https://www.zhihu.com/zvideo/{}", zvideo.id),
+        title: Some(zvideo.title.clone()),
+        excerpt: (!zvideo.description.is_empty()).then(|| description.clone()),
+        content: Some(description),
+        thumbnail: thumbnail.clone(),
+        author: zvideo.author,
+        attachment: Some(Attachment {
+            type_: "video".to_string(),
+            video: AttachmentVideo {
+                title: zvideo.title,
+                play_count: zvideo.play_count,
+                video_info: VideoInfo {
+                    thumbnail: thumbnail.unwrap_or_default(),
+                    playlist: zvideo.video.playlist,
+                },
+            },
+        }),
+        created_time: zvideo.created_at,
+        updated_time: zvideo.updated_at,
+        voteup_count: zvideo.voteup_count,
+        comment_count: zvideo.comment_count,
+        ext: json!({}),
+        ..Default::default()
+    }
+}
+
+fn pin_images(pin: &Pin) -> Markup {
+    let images = pin.content.iter().filter_map(|content| match content {
+        PinContent::Image {
+            url,
+            original_url,
+            width,
+            ..
+        } => Some((url, original_url, (*width > 0).then_some(width))),
+        _ => None,
+    });
+
+    html! {
+        @for (url, original_url, width) in images {
+            figure {
+                img src=(url) data-original=(original_url) data-rawwidth=[width];
+            }
+        }
+    }
+}
+
+/// Lists of answers, articles or pins themselves, like a member's answers.
+pub fn parse_list(value: &Value) -> ApiResults<TimelineItem> {
+    let mut results = ApiResults {
+        paging: serde_json::from_value(value["paging"].clone()).unwrap_or_default(),
+        ..Default::default()
+    };
 
     let data = value["data"].as_array();
     if let Some(data) = data {
-        for item in data {
-            let str = item["target"].to_string();
-            let jd = &mut serde_json::Deserializer::from_str(&str);
+        results.data.extend(data.iter().filter_map(parse_item));
+    }
 
-            let object: TimelineItem = match serde_path_to_error::deserialize(jd) {
-                Ok(object) => object,
+    results
+}
+
+pub fn parse_hot_list(value: &Value) -> ApiResults<HotItem> {
+    let mut results = ApiResults {
+        paging: serde_json::from_value(value["paging"].clone()).unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let data = value["data"].as_array();
+    if let Some(data) = data {
+        for (index, item) in data.iter().enumerate() {
+            let target = &item["target"];
+            let question: Question = match serde_json::from_value(target.clone()) {
+                Ok(question) => question,
                 Err(err) => {
-                    error!("parse timteline item error: {:?}", err);
+                    error!("parse hot list item error: {:?}", err);
                     continue;
                 }
             };
-
-            match object.type_.as_str() {
-                "answer" | "article" => {
-                    results.data.push(object);
-                }
-                ty => {
-                    debug!("find unsupport type: {}", ty);
-                }
+            if question.id.is_empty() {
+                continue;
             }
+
+            results.data.push(HotItem {
+                rank: index + 1,
+                heat: item["detail_text"].as_str().unwrap_or_default().to_string(),
+                question,
+                excerpt: target["excerpt"].as_str().unwrap_or_default().to_string(),
+                thumbnail: item
+                    .pointer("/children/0/thumbnail")
+                    .and_then(|v| v.as_str())
+                    .filter(|url| !url.is_empty())
+                    .map(String::from),
+            });
         }
     }
 
@@ -40,9 +195,10 @@ pub fn parse_timeline(value: &Value) -> ApiResults<TimelineItem> {
 }
 
 pub fn parse_search(value: &Value) -> ApiResults<SearchItem> {
-    let mut results = ApiResults::default();
-
-    results.paging = serde_json::from_value(value["paging"].clone()).unwrap_or_default();
+    let mut results = ApiResults {
+        paging: serde_json::from_value(value["paging"].clone()).unwrap_or_default(),
+        ..Default::default()
+    };
 
     let data = value["data"].as_array();
     if let Some(data) = data {
@@ -54,25 +210,20 @@ pub fn parse_search(value: &Value) -> ApiResults<SearchItem> {
                     .push(SearchItem::RelevantQuery(item["query_list"].clone()));
                 continue;
             } else if ty == "search_result" {
-                let str = item["object"].to_string();
-                let jd = &mut serde_json::Deserializer::from_str(&str);
-
-                let object: TimelineItem = match serde_path_to_error::deserialize(jd) {
-                    Ok(object) => object,
-                    Err(err) => {
-                        error!("parse search result error: {:?}", err);
-                        continue;
-                    }
+                let Some(mut object) = parse_item(&item["object"]) else {
+                    continue;
                 };
-
-                match object.type_.as_str() {
-                    "answer" | "article" => {
-                        results.data.push(SearchItem::SearchResult(object));
-                    }
-                    ty => {
-                        debug!("find unsupport type: {}", ty);
-                    }
+                if object.thumbnail.is_none() {
+                    object.thumbnail = object
+                        .ext
+                        .pointer("/thumbnail_info/thumbnails/0/url")
+                        .and_then(|v| v.as_str())
+                        .map(String::from);
                 }
+
+                results
+                    .data
+                    .push(SearchItem::SearchResult(Box::new(object)));
             }
         }
     }
@@ -115,3 +266,315 @@ where
 
     serde_json::from_value(value.clone()).ok()
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::types::{Author, Paging};
+
+    pub(crate) fn fixture(path: &str) -> String {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), path);
+        std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path, err))
+    }
+
+    pub(crate) fn fixture_json(path: &str) -> Value {
+        serde_json::from_str(&fixture(path)).unwrap()
+    }
+
+    fn rust_question() -> Question {
+        Question {
+            id: "19550225".to_string(),
+            title: "学习 Rust 值得吗？".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn zhangsan() -> Author {
+        Author {
+            id: "a1b2c3".to_string(),
+            url_token: "zhangsan".to_string(),
+            avatar_url: "https://pic1.zhimg.com/v2-avatar_l.jpg".to_string(),
+            name: "张三".to_string(),
+            headline: "系统程序员".to_string(),
+        }
+    }
+
+    #[test]
+    fn timeline_recommend() {
+        let results = parse_timeline(&fixture_json(
+            "www.zhihu.com/api/v3/feed/topstory/recommend.json",
+        ));
+
+        assert_eq!(
+            results.paging,
+            Paging {
+                is_end: Some(false),
+                is_start: None,
+                next: "https://www.zhihu.com/api/v3/feed/topstory/recommend?session_token=abc&page_number=2".to_string(),
+                previous: "https://www.zhihu.com/api/v3/feed/topstory/recommend?session_token=abc&page_number=0".to_string(),
+                totals: 0,
+            }
+        );
+
+        // the malformed answer fails to deserialize
+        assert_eq!(results.data.len(), 4);
+        assert_eq!(results.data[2].type_, "pin");
+
+        let zvideo = &results.data[3];
+        assert_eq!(zvideo.type_, "zvideo");
+        assert_eq!(zvideo.title.as_deref(), Some("一个视频"));
+        assert_eq!(
+            zvideo.thumbnail.as_deref(),
+            Some("https://pic4.zhimg.com/v2-zvideo_cover.jpg")
+        );
+        assert_eq!(zvideo.created_time, Some(1681100000));
+        let video = &zvideo.attachment.as_ref().unwrap().video;
+        assert_eq!(video.play_count, 4096);
+        assert_eq!(
+            video.video_info.playlist.hd.as_ref().unwrap().url,
+            "https://vdn.vzuu.com/HD/5000001.mp4"
+        );
+
+        assert_eq!(
+            results.data[0],
+            TimelineItem {
+                id: "1000001".to_string(),
+                type_: "answer".to_string(),
+                url: "https://api.zhihu.com/answers/1000001".to_string(),
+                thumbnail: Some("https://pic1.zhimg.com/v2-thumb_720w.jpg".to_string()),
+                excerpt: Some("先说结论：值得。[赞同]".to_string()),
+                question: Some(rust_question()),
+                author: Some(zhangsan()),
+                created_time: Some(1680000000),
+                updated_time: Some(1680086400),
+                voteup_count: 1024,
+                comment_count: 32,
+                ext: json!({}),
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(
+            results.data[1],
+            TimelineItem {
+                id: "2000001".to_string(),
+                type_: "article".to_string(),
+                url: "https://api.zhihu.com/articles/2000001".to_string(),
+                image_url: "https://pic2.zhimg.com/v2-title_image.jpg".to_string(),
+                title: Some("用 axum 写一个知乎客户端".to_string()),
+                excerpt: Some("本文介绍如何用 axum 和 maud 写一个轻量的知乎客户端。".to_string()),
+                author: Some(Author {
+                    avatar_url: "https://pic2.zhimg.com/v2-avatar2_l.jpg".to_string(),
+                    name: "李四".to_string(),
+                    ..Default::default()
+                }),
+                created_time: Some(1679000000),
+                updated_time: Some(1679500000),
+                voteup_count: 256,
+                ext: json!({}),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn hot_list() {
+        let results = parse_hot_list(&fixture_json(
+            "www.zhihu.com/api/v3/feed/topstory/hot-lists/total.json",
+        ));
+
+        // the entry without a question is dropped
+        assert_eq!(results.data.len(), 2);
+        assert_eq!(
+            results.data[0],
+            HotItem {
+                rank: 1,
+                heat: "1024 万热度".to_string(),
+                question: Question {
+                    answer_count: 128,
+                    comment_count: 12,
+                    ..rust_question()
+                },
+                excerpt: "最近在考虑学习一门新的系统编程语言。".to_string(),
+                thumbnail: Some("https://pic1.zhimg.com/v2-hot_thumb.jpg".to_string()),
+            }
+        );
+        assert_eq!(results.data[1].rank, 2);
+        assert_eq!(results.data[1].thumbnail, None);
+    }
+
+    #[test]
+    fn timeline_question_feeds() {
+        let results = parse_timeline(&fixture_json(
+            "www.zhihu.com/api/v4/questions/19550225/feeds.json",
+        ));
+
+        assert_eq!(results.paging.is_start, Some(true));
+        assert_eq!(results.paging.is_end, Some(false));
+        assert_eq!(results.paging.totals, 42);
+        assert_eq!(
+            results
+                .data
+                .iter()
+                .map(|i| i.id.as_str())
+                .collect::<Vec<_>>(),
+            ["1000001", "1000003"]
+        );
+
+        let answer = &results.data[0];
+        assert_eq!(answer.question, Some(rust_question()));
+        assert_eq!(answer.author, Some(zhangsan()));
+        assert!(answer
+            .content
+            .as_deref()
+            .unwrap()
+            .starts_with("<p>先说结论：值得。[赞同]</p><figure"));
+        assert_eq!(answer.excerpt.as_deref(), Some("先说结论：值得。"));
+    }
+
+    #[test]
+    fn pin_as_item() {
+        let pin: Pin =
+            serde_json::from_value(fixture_json("www.zhihu.com/api/v4/pins/4000001.json")).unwrap();
+        assert_eq!(pin.likes(), 88);
+        assert_eq!(
+            pin.images(),
+            [
+                "https://pic1.zhimg.com/v2-pin1_r.jpg",
+                "https://pic2.zhimg.com/v2-pin2_r.jpg"
+            ]
+        );
+        assert_eq!(pin.content.last(), Some(&PinContent::Other));
+
+        let item = pin_item(pin);
+        assert_eq!(item.id, "4000001");
+        assert_eq!(item.voteup_count, 88);
+        assert_eq!(item.comment_count, 5);
+        assert_eq!(
+            item.thumbnail.as_deref(),
+            Some("https://pic1.zhimg.com/v2-pin1_r.jpg")
+        );
+        let content = item.content.as_deref().unwrap();
+        assert!(content.starts_with("周末去爬山了，天气很好。<br>转发一下朋友的想法。<figure><img src=\"https://pic1.zhimg.com/v2-pin1_720w.jpg\" data-original=\"https://pic1.zhimg.com/v2-pin1_r.jpg\" data-rawwidth=\"1920\"></figure>"));
+        assert!(content.contains("<blockquote><p>@赵六:</p>山顶的日出<figure>"));
+    }
+
+    #[test]
+    fn search_results() {
+        let results = parse_search(&fixture_json("www.zhihu.com/api/v4/search_v3.json"));
+
+        assert_eq!(results.paging.is_end, Some(true));
+        // the topic result and the ad are dropped
+        assert_eq!(results.data.len(), 2);
+
+        assert_eq!(
+            results.data[0],
+            SearchItem::RelevantQuery(json!([
+                { "query": "rust 入门" },
+                { "query": "rust 和 go" }
+            ]))
+        );
+        assert_eq!(
+            results.data[1],
+            SearchItem::SearchResult(Box::new(TimelineItem {
+                id: "1000001".to_string(),
+                type_: "answer".to_string(),
+                url: "https://api.zhihu.com/answers/1000001".to_string(),
+                excerpt: Some("先说结论：值得。学 <em>rust</em> 不亏。".to_string()),
+                thumbnail: Some("https://pic3.zhimg.com/v2-search_thumb.jpg".to_string()),
+                question: Some(Question {
+                    title: "学习 <em>Rust</em> 值得吗？".to_string(),
+                    ..rust_question()
+                }),
+                author: Some(zhangsan()),
+                created_time: Some(1680000000),
+                updated_time: Some(1680086400),
+                voteup_count: 1024,
+                comment_count: 32,
+                ext: json!({
+                    "thumbnail_info": {
+                        "count": 1,
+                        "thumbnails": [
+                            { "url": "https://pic3.zhimg.com/v2-search_thumb.jpg", "type": "image" }
+                        ]
+                    }
+                }),
+                ..Default::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn entity_question() {
+        let html = fixture("www.zhihu.com/question/19550225.html");
+
+        let question: Question = parse_entity_data(&html, "questions", "19550225").unwrap();
+        assert_eq!(
+            question,
+            Question {
+                detail: "<p>工作中主要写 Go，想学点<b>新东西</b>。</p>".to_string(),
+                answer_count: 42,
+                comment_count: 3,
+                voteup_count: 7,
+                ..rust_question()
+            }
+        );
+
+        assert!(parse_entity_data::<Question>(&html, "questions", "1").is_none());
+    }
+
+    #[test]
+    fn entity_answer() {
+        let html = fixture("www.zhihu.com/question/19550225/answer/1000001.html");
+        let data = parse_inital_data(&html).unwrap();
+
+        let answer: TimelineItem =
+            serde_json::from_value(data["initialState"]["entities"]["answers"]["1000001"].clone())
+                .unwrap();
+        assert_eq!(
+            answer,
+            TimelineItem {
+                id: "1000001".to_string(),
+                type_: "answer".to_string(),
+                url: "https://www.zhihu.com/answer/1000001".to_string(),
+                content: Some("<p>先说结论：值得。[赞同]</p>".to_string()),
+                excerpt: Some("先说结论：值得。".to_string()),
+                author: Some(zhangsan()),
+                created_time: Some(1680000000),
+                updated_time: Some(1680086400),
+                voteup_count: 1024,
+                comment_count: 32,
+                ext: json!({}),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn entity_article() {
+        let html = fixture("zhuanlan.zhihu.com/p/2000001.html");
+
+        let article: TimelineItem = parse_entity_data(&html, "articles", "2000001").unwrap();
+        assert_eq!(article.id, "2000001");
+        assert_eq!(article.title.as_deref(), Some("用 axum 写一个知乎客户端"));
+        assert_eq!(
+            article.image_url,
+            "https://pic2.zhimg.com/v2-title_image.jpg"
+        );
+        assert_eq!(article.created_time, Some(1679000000));
+        assert_eq!(article.updated_time, Some(1679500000));
+        assert_eq!(article.ext, json!({}));
+        assert_eq!(article.author.unwrap().name, "李四");
+        let column = article.column.unwrap();
+        assert_eq!(
+            (column.id.as_str(), column.title.as_str()),
+            ("rust-notes", "Rust 笔记")
+        );
+    }
+
+    #[test]
+    fn missing_initial_data() {
+        assert!(parse_inital_data("<html></html>").is_none());
+        assert!(parse_entity_data::<Question>("<html></html>", "questions", "1").is_none());
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageReader, Limits,
+};
+
+use crate::{images::Image, types::Error};
+
+/// Widths images are resized to, requests are rounded up to the next one.
+pub const WIDTHS: [u32; 4] = [360, 720, 1080, 1440];
+/// Content column width, see the `max-w-2xl` in `routes::layout`.
+pub const SIZES: &str = "(max-width: 42rem) 100vw, 42rem";
+/// Bytes of resized images kept in memory.
+pub const DEFAULT_CAPACITY: usize = 64 * 1024 * 1024;
+
+const JPEG_QUALITY: u8 = 80;
+const AVIF_QUALITY: u8 = 60;
+/// rav1e speed, 10 is the fastest.
+const AVIF_SPEED: u8 = 10;
+const MAX_DIMENSION: u32 = 16384;
+
+/// The preset used for a requested width.
+pub fn preset(width: u32) -> u32 {
+    WIDTHS
+        .iter()
+        .copied()
+        .find(|&preset| preset >= width)
+        .unwrap_or(WIDTHS[WIDTHS.len() - 1])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Avif,
+    Webp,
+    Jpeg,
+    Png,
+}
+
+/// Formats the browser takes besides jpeg and png, from its `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Accepted {
+    avif: bool,
+    webp: bool,
+}
+
+impl Accepted {
+    pub fn parse(accept: &str) -> Self {
+        Self {
+            avif: accept.contains("image/avif"),
+            webp: accept.contains("image/webp"),
+        }
+    }
+
+    /// Avif when the browser takes it. Otherwise lossless sources become
+    /// webp, which is only lossless here, and photos stay jpeg.
+    pub fn format(self, source: &str) -> Format {
+        let lossless = matches!(source, "image/png" | "image/webp");
+        if self.avif {
+            Format::Avif
+        } else if lossless && self.webp {
+            Format::Webp
+        } else if lossless {
+            Format::Png
+        } else {
+            Format::Jpeg
+        }
+    }
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Avif => "image/avif",
+            Format::Webp => "image/webp",
+            Format::Jpeg => "image/jpeg",
+            Format::Png => "image/png",
+        }
+    }
+}
+
+/// Whether `image` can be resized, animations and vector images are served as they are.
+pub fn is_resizable(image: &Image) -> bool {
+    matches!(
+        image.content_type.as_str(),
+        "image/jpeg" | "image/png" | "image/webp"
+    )
+}
+
+/// Scales `image` down to `width`, never up, and encodes it as `format`.
+pub fn resize(image: &Image, width: u32, format: Format) -> Result<Image, Error> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+
+    let mut reader = ImageReader::new(Cursor::new(&image.bytes))
+        .with_guessed_format()
+        .map_err(anyhow::Error::from)?;
+    reader.limits(limits);
+    let mut decoded = reader.decode().map_err(anyhow::Error::from)?;
+
+    if decoded.width() > width {
+        let height = (decoded.height() as u64 * width as u64 / decoded.width() as u64).max(1);
+        decoded = decoded.resize_exact(width, height as u32, FilterType::Triangle);
+    }
+
+    let mut bytes = Vec::new();
+    let written = match format {
+        Format::Avif => decoded.write_with_encoder(AvifEncoder::new_with_speed_quality(
+            &mut bytes,
+            AVIF_SPEED,
+            AVIF_QUALITY,
+        )),
+        Format::Webp => DynamicImage::ImageRgba8(decoded.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut bytes)),
+        Format::Jpeg => DynamicImage::ImageRgb8(decoded.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)),
+        Format::Png => decoded.write_with_encoder(PngEncoder::new(&mut bytes)),
+    };
+    written.map_err(anyhow::Error::from)?;
+
+    Ok(Image {
+        url: image.url.clone(),
+        content_type: format.content_type().to_string(),
+        bytes,
+    })
+}
+
+struct Entry {
+    image: Arc<Image>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    /// Bytes of all images.
+    used: usize,
+    map: HashMap<(String, u32, Accepted), Entry>,
+}
+
+/// Resized images in memory, keyed by url, width and the formats the browser takes.
+///
+/// Once `capacity` bytes are used the least recently used images are dropped.
+pub struct ResizeCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+    clock: AtomicU64,
+}
+
+impl ResizeCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Default::default(),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, url: &str, width: u32, accepted: Accepted) -> Option<Arc<Image>> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.map.get_mut(&(url.to_string(), width, accepted))?;
+        entry.last_used = self.clock.fetch_add(1, Ordering::Relaxed);
+        Some(entry.image.clone())
+    }
+
+    pub fn insert(&self, width: u32, accepted: Accepted, image: Arc<Image>) {
+        let size = image.bytes.len();
+        if size > self.capacity {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        while entries.used + size > self.capacity {
+            let lru = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match lru.and_then(|lru| entries.map.remove(&lru)) {
+                Some(evicted) => entries.used -= evicted.image.bytes.len(),
+                None => break,
+            }
+        }
+
+        let key = (image.url.clone(), width, accepted);
+        let entry = Entry {
+            image,
+            last_used: self.clock.fetch_add(1, Ordering::Relaxed),
+        };
+        if let Some(replaced) = entries.map.insert(key, entry) {
+            entries.used -= replaced.image.bytes.len();
+        }
+        entries.used += size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GenericImageView, RgbImage};
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Image {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_with_encoder(PngEncoder::new(&mut bytes))
+            .unwrap();
+        Image {
+            url: "https://pic1.zhimg.com/v2-a.png".to_string(),
+            content_type: "image/png".to_string(),
+            bytes,
+        }
+    }
+
+    #[test]
+    fn presets_and_formats() {
+        assert_eq!(preset(1), 360);
+        assert_eq!(preset(720), 720);
+        assert_eq!(preset(721), 1080);
+        assert_eq!(preset(5000), 1440);
+
+        let all = Accepted::parse("image/avif,image/webp,*/*");
+        assert_eq!(all.format("image/jpeg"), Format::Avif);
+        let webp = Accepted::parse("image/webp,*/*");
+        assert_eq!(webp.format("image/png"), Format::Webp);
+        assert_eq!(webp.format("image/jpeg"), Format::Jpeg);
+        assert_eq!(Accepted::parse("*/*").format("image/png"), Format::Png);
+    }
+
+    #[test]
+    fn scale_down_only() {
+        let resized = resize(&png(1000, 500), 360, Format::Webp).unwrap();
+        assert_eq!(resized.content_type, "image/webp");
+        let decoded = image::load_from_memory(&resized.bytes).unwrap();
+        assert_eq!(decoded.dimensions(), (360, 180));
+
+        let resized = resize(&png(100, 50), 360, Format::Jpeg).unwrap();
+        let decoded = image::load_from_memory(&resized.bytes).unwrap();
+        assert_eq!(decoded.dimensions(), (100, 50));
+    }
+
+    #[test]
+    fn cache_evicts_by_size() {
+        let image = |url: &str, size: usize| {
+            Arc::new(Image {
+                url: url.to_string(),
+                content_type: "image/jpeg".to_string(),
+                bytes: vec![0; size],
+            })
+        };
+        let jpeg = Accepted::parse("*/*");
+        let cache = ResizeCache::new(100);
+        cache.insert(360, jpeg, image("a", 40));
+        cache.insert(360, jpeg, image("b", 40));
+        assert!(cache.get("a", 360, jpeg).is_some());
+
+        cache.insert(360, jpeg, image("c", 40));
+        assert!(cache.get("a", 360, jpeg).is_some());
+        assert!(cache.get("b", 360, jpeg).is_none());
+        assert!(cache.get("a", 720, jpeg).is_none());
+        assert!(cache.get("a", 360, Accepted::parse("image/avif")).is_none());
+    }
+}
@@ -0,0 +1,164 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+
+use crate::{
+    backend::{UpstreamRequest, UpstreamResponse, ZhihuBackend},
+    types::Error,
+};
+
+pub const DEFAULT_RETRIES: u32 = 2;
+pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(200);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Retries every upstream request (they are all idempotent GETs) on network errors
+/// and 5xx responses, waiting `backoff`, `2 * backoff`, `4 * backoff`... in between.
+pub struct RetryBackend {
+    inner: Arc<dyn ZhihuBackend>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl RetryBackend {
+    pub fn new(inner: Arc<dyn ZhihuBackend>, retries: u32, backoff: Duration) -> Self {
+        Self {
+            inner,
+            retries,
+            backoff,
+        }
+    }
+
+    fn delay(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(MAX_BACKOFF)
+    }
+}
+
+fn retryable(result: &Result<UpstreamResponse, Error>) -> bool {
+    match result {
+        Ok(response) => response.status >= 500,
+        Err(Error::Reqwest(err)) => err.is_timeout() || err.is_connect() || err.is_request(),
+        Err(_) => false,
+    }
+}
+
+#[async_trait]
+impl ZhihuBackend for RetryBackend {
+    async fn fetch(&self, request: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+        let mut retry = 0;
+
+        loop {
+            let result = self.inner.fetch(request.clone()).await;
+            if !retryable(&result) {
+                if retry > 0 {
+                    info!("{} succeeded after {} retries", request.url, retry);
+                }
+                return result;
+            }
+
+            if retry >= self.retries {
+                if retry == 0 {
+                    return result;
+                }
+                warn!("{} failed after {} retries", request.url, retry);
+                let last = match result {
+                    Ok(response) => Error::Upstream(response.status),
+                    Err(err) => err,
+                };
+                return Err(Error::Retried {
+                    retries: retry,
+                    last: Box::new(last),
+                });
+            }
+
+            let delay = self.delay(retry);
+            retry += 1;
+            match &result {
+                Ok(response) => warn!(
+                    "{} responded with status {}, retry {}/{} in {:?}",
+                    request.url, response.status, retry, self.retries, delay
+                ),
+                Err(err) => warn!(
+                    "{} failed: {}, retry {}/{} in {:?}",
+                    request.url, err, retry, self.retries, delay
+                ),
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::backend::Endpoint;
+
+    /// Responds with 502 until `failures` requests have been made.
+    struct Flaky {
+        calls: AtomicU32,
+        failures: u32,
+    }
+
+    #[async_trait]
+    impl ZhihuBackend for Flaky {
+        async fn fetch(&self, _: UpstreamRequest) -> Result<UpstreamResponse, Error> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(UpstreamResponse {
+                status: if call < self.failures { 502 } else { 200 },
+                body: String::new(),
+            })
+        }
+    }
+
+    fn request() -> UpstreamRequest {
+        UpstreamRequest::new(Endpoint::Article, "https://zhuanlan.zhihu.com/p/1")
+    }
+
+    #[tokio::test]
+    async fn retry_until_success() {
+        let flaky = Arc::new(Flaky {
+            calls: AtomicU32::new(0),
+            failures: 2,
+        });
+        let backend = RetryBackend::new(flaky.clone(), 2, Duration::from_millis(1));
+
+        let response = backend.fetch(request()).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn give_up() {
+        let flaky = Arc::new(Flaky {
+            calls: AtomicU32::new(0),
+            failures: 10,
+        });
+        let backend = RetryBackend::new(flaky.clone(), 2, Duration::from_millis(1));
+
+        let err = backend.fetch(request()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Retried { retries: 2, ref last } if matches!(**last, Error::Upstream(502))
+        ));
+        assert_eq!(err.status(), http::StatusCode::BAD_GATEWAY);
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn backoff() {
+        let backend = RetryBackend::new(
+            Arc::new(Flaky {
+                calls: AtomicU32::new(0),
+                failures: 0,
+            }),
+            10,
+            Duration::from_millis(200),
+        );
+        assert_eq!(backend.delay(0), Duration::from_millis(200));
+        assert_eq!(backend.delay(2), Duration::from_millis(800));
+        assert_eq!(backend.delay(9), MAX_BACKOFF);
+    }
+}
@@ -1,56 +1,63 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use axum::{
-    extract::{Path, Query},
+    extract::{Path, Query, State},
+    middleware::Next,
     response::{AppendHeaders, IntoResponse, Redirect, Response},
 };
-use http::{header, StatusCode, Uri};
+use http::{header, HeaderValue, Request, StatusCode, Uri};
 use maud::{Markup, PreEscaped};
-use once_cell::sync::Lazy;
-use reqwest::Client;
 use serde_json::Value;
 
 use crate::{
+    archive::{Archive, ArchiveKind},
+    assets,
+    backend::ZhihuBackend,
+    config::{self, BaseUrl},
+    cookies::CookiePool,
+    health::{self, HealthState},
+    images::ImageClient,
     parser,
-    types::{Comment, Error, Paging, Question, SearchItem, TimelineItem},
+    types::{
+        AnswerPage, ApiResults, ArticlePage, Column, ColumnPage, Comment, CommentsPage, Error,
+        HotItem, Paging, People, PeoplePage, Pin, PinPage, Question, QuestionPage, SearchItem,
+        TimelineItem, VideoUrls, ZVideo, ZVideoPage,
+    },
     views,
 };
 
-macro_rules! headers {
-	{ $($key:expr => $value:expr),+ } => {
-		{
-			let mut m = http::HeaderMap::new();
-			$(
-				if let Ok(val) = http::header::HeaderValue::from_str($value) {
-					m.insert($key, val);
-				}
-			)+
-			m
-		}
-	 };
-}
-static CLIENT: Lazy<Client> = Lazy::new(|| {
-    Client::builder()
-        .default_headers(headers! {
-            "cookie" => &(std::env::var("ZHIHU_COOKIE").unwrap_or_else(|_|format!("d_c0=AHAYQW4aKRaPTkeCwzmIwGqf-AOazW4-dpM=|1673495514")))
-        })
-        .build()
-        .unwrap()
-});
+#[derive(Clone)]
+pub struct AppState {
+    pub backend: Arc<dyn ZhihuBackend>,
+    pub cookies: Arc<CookiePool>,
+    pub archive: Arc<Archive>,
+    pub images: Arc<ImageClient>,
+    pub base_url: BaseUrl,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// Serves the test fixtures with an in-memory archive.
+    pub fn fixtures() -> Self {
+        Self {
+            backend: Arc::new(crate::backend::FixtureBackend::new(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures"
+            ))),
+            cookies: Arc::new(CookiePool::new(vec![], Default::default())),
+            archive: Arc::new(Archive::memory().unwrap()),
+            images: Arc::new(ImageClient::new(&Default::default()).unwrap()),
+            base_url: Default::default(),
+        }
+    }
+}
 
 pub async fn index() -> impl IntoResponse {
     Redirect::to("/recommend")
 }
 
-pub async fn recommend() -> Result<Markup, Error> {
-    let response = CLIENT
-        .get("https://www.zhihu.com/api/v3/feed/topstory/recommend")
-        .send()
-        .await?
-        .json()
-        .await?;
-
-    let results = parser::parse_timeline(&response);
+pub async fn recommend(state: State<AppState>) -> Result<Markup, Error> {
+    let results = load_recommend(&state, json!({})).await?;
 
     Ok(layout(
         html! {
@@ -69,48 +76,83 @@ pub async fn recommend() -> Result<Markup, Error> {
     ))
 }
 
+/// Hot list categories, the zhihu name and ours.
+pub const HOT_CATEGORIES: [(&str, &str); 10] = [
+    ("total", "全站"),
+    ("science", "科学"),
+    ("digital", "数码"),
+    ("sport", "体育"),
+    ("fashion", "时尚"),
+    ("film", "影视"),
+    ("school", "校园"),
+    ("car", "汽车"),
+    ("depth", "深度"),
+    ("focus", "国际"),
+];
+
+pub async fn hot(state: State<AppState>, query: Query<Value>) -> Result<Markup, Error> {
+    let category = query["category"].as_str().unwrap_or("total");
+    let results = load_hot(&state, category).await?;
+
+    Ok(layout(
+        html! {
+            div class="p-4 mb-2 bg-white text-sm" {
+                @for (name, label) in HOT_CATEGORIES {
+                    @if name == category {
+                        span class="inline-block mr-2 mb-1 px-2 py-1 rounded bg-gray-200 font-bold" { (label) }
+                    } @else {
+                        a class="inline-block mr-2 mb-1 px-2 py-1 rounded text-gray-500" href=(format!("/hot?category={}", name)) { (label) }
+                    }
+                }
+            }
+            @if results.data.is_empty() {
+                div class="p-4 mb-2 bg-white text-center font-bold" { "暂无数据"}
+            }
+            @for item in &results.data {
+                (views::hot_item(item))
+            }
+        },
+        Some("热榜"),
+    ))
+}
+
+/// Unknown categories are not found rather than sent to zhihu.
+pub async fn load_hot(state: &AppState, category: &str) -> Result<ApiResults<HotItem>, Error> {
+    if !HOT_CATEGORIES.iter().any(|(name, _)| *name == category) {
+        return Err(Error::NotFound);
+    }
+
+    let response = state.backend.hot_list(category).await?;
+    Ok(parser::parse_hot_list(&response))
+}
+
+pub async fn load_recommend(
+    state: &AppState,
+    query: Value,
+) -> Result<ApiResults<TimelineItem>, Error> {
+    let response = state.backend.recommend(&query).await?;
+
+    Ok(parser::parse_timeline(&response))
+}
+
 pub async fn question(
+    state: State<AppState>,
     qid: Path<(String,)>,
     query: Query<Value>,
     uri: Uri,
 ) -> Result<Markup, Error> {
-    let qid = qid.0 .0;
-    let mut query = query.0;
-
-    if !query["include"].is_string() {
-        query["include"] = json!("data[*].is_normal,admin_closed_comment,reward_info,is_collapsed,annotation_action,annotation_detail,collapse_reason,is_sticky,collapsed_by,suggest_edit,comment_count,can_comment,content,attachment,voteup_count,reshipment_settings,comment_permission,created_time,updated_time,review_info,relevant_info,question,excerpt,is_labeled,paid_info,paid_info_content,reaction_instruction,relationship.is_authorized,is_author,voting,is_thanked,is_nothelp,is_recognized;data[*].mark_infos[*].url;data[*].author.follower_count,vip_info,badge[*].topics;data[*].settings.table_of_content.enable");
-    }
-
-    let html = CLIENT
-        .get(format!("https://www.zhihu.com/question/{}", qid))
-        .send()
-        .await?
-        .text()
-        .await?;
-
-    let results: Value = CLIENT
-        .get(format!(
-            "https://www.zhihu.com/api/v4/questions/{}/feeds",
-            qid
-        ))
-        .query(&query)
-        .send()
-        .await?
-        .json()
-        .await?;
-
-    let question: Question =
-        parser::parse_entity_data(&html, "questions", &qid).unwrap_or_else(|| {
-            debug!("unable to find question of {}", qid);
-            Default::default()
-        });
-    let results = parser::parse_timeline(&results);
+    let QuestionPage {
+        question,
+        answers: results,
+        archived_at,
+    } = load_question(&state, &qid.0 .0, query.0).await?;
 
     Ok(layout(
         html! {
+            (views::archived(archived_at))
             (views::question(&question, false))
 
-            (render_prev(&results.paging, &uri.path(), html! {
+            (render_prev(&results.paging, uri.path(), html! {
                 div class="p-4 mb-2 bg-white text-center font-bold" { "查看上一页" }
             }))
 
@@ -118,7 +160,7 @@ pub async fn question(
                 (views::answer(item, true))
             }
 
-            (render_next(&results.paging, &uri.path(), html! {
+            (render_next(&results.paging, uri.path(), html! {
                 div class="p-4 my-2 bg-white text-center font-base" { "查看更多 " (question.answer_count) " 个答案" }
             }))
 
@@ -127,27 +169,71 @@ pub async fn question(
     ))
 }
 
-pub async fn answer(p: Path<(String, String)>, query: Query<Value>) -> Result<Markup, Error> {
-    let (qid, aid) = p.0;
-    let query = query.0;
+pub async fn load_question(
+    state: &AppState,
+    qid: &str,
+    mut query: Value,
+) -> Result<QuestionPage, Error> {
+    if !query["include"].is_string() {
+        query["include"] = json!("data[*].is_normal,admin_closed_comment,reward_info,is_collapsed,annotation_action,annotation_detail,collapse_reason,is_sticky,collapsed_by,suggest_edit,comment_count,can_comment,content,attachment,voteup_count,reshipment_settings,comment_permission,created_time,updated_time,review_info,relevant_info,question,excerpt,is_labeled,paid_info,paid_info_content,reaction_instruction,relationship.is_authorized,is_author,voting,is_thanked,is_nothelp,is_recognized;data[*].mark_infos[*].url;data[*].author.follower_count,vip_info,badge[*].topics;data[*].settings.table_of_content.enable");
+    }
 
-    let html = CLIENT
-        .get(format!(
-            "https://www.zhihu.com/question/{}/answer/{}",
-            qid, aid
-        ))
-        .query(&query)
-        .send()
-        .await?
-        .text()
-        .await?;
+    let fetched = async {
+        let html = state.backend.question(qid).await?;
+        let results = state.backend.question_feeds(qid, &query).await?;
 
-    let initial_data = parser::parse_inital_data(&html).unwrap_or_default();
-    let que = initial_data["initialState"]["entities"]["questions"][&qid].clone();
-    let answer = initial_data["initialState"]["entities"]["answers"][&aid].clone();
+        let question: Question = parser::parse_entity_data(&html, "questions", qid)
+            .ok_or_else(|| Error::missing("question", qid))?;
+        Ok::<_, Error>((question, parser::parse_timeline(&results)))
+    }
+    .await;
 
-    let que: Question = serde_json::from_value(que)?;
-    let answer: TimelineItem = serde_json::from_value(answer)?;
+    match fetched {
+        Ok((question, answers)) => {
+            state.archive.put(ArchiveKind::Question, qid, "", &question);
+            for item in &answers.data {
+                state.archive.put(ArchiveKind::Answer, &item.id, qid, item);
+            }
+            Ok(QuestionPage {
+                question,
+                answers,
+                archived_at: None,
+            })
+        }
+        Err(err) => match state.archive.get::<Question>(ArchiveKind::Question, qid) {
+            Some(archived) => {
+                warn!("serve archived question {}: {}", qid, err);
+                let answers = ApiResults {
+                    data: state
+                        .archive
+                        .children(ArchiveKind::Answer, qid)
+                        .into_iter()
+                        .map(|a| a.value)
+                        .collect(),
+                    ..Default::default()
+                };
+                Ok(QuestionPage {
+                    question: archived.value,
+                    answers,
+                    archived_at: Some(archived.archived_at),
+                })
+            }
+            None => Err(err),
+        },
+    }
+}
+
+pub async fn answer(
+    state: State<AppState>,
+    p: Path<(String, String)>,
+    query: Query<Value>,
+) -> Result<Markup, Error> {
+    let (qid, aid) = p.0;
+    let AnswerPage {
+        question: que,
+        answer,
+        archived_at,
+    } = load_answer(&state, &qid, &aid, query.0).await?;
 
     let q_href = format!("/question/{}", que.id);
     let check_more = html! {
@@ -162,6 +248,7 @@ pub async fn answer(p: Path<(String, String)>, query: Query<Value>) -> Result<Ma
 
     Ok(layout(
         html! {
+            (views::archived(archived_at))
             (views::question(&que, true))
             (check_more)
             (views::answer(&answer, true))
@@ -175,37 +262,93 @@ pub async fn answer(p: Path<(String, String)>, query: Query<Value>) -> Result<Ma
     ))
 }
 
-pub async fn article(aid: Path<(String,)>) -> Result<Markup, Error> {
-    let aid = aid.0 .0;
+pub async fn load_answer(
+    state: &AppState,
+    qid: &str,
+    aid: &str,
+    query: Value,
+) -> Result<AnswerPage, Error> {
+    let fetched = async {
+        let html = state.backend.answer(qid, aid, &query).await?;
 
-    let html = CLIENT
-        .get(format!("https://zhuanlan.zhihu.com/p/{}", aid))
-        .send()
-        .await?
-        .text()
-        .await?;
+        let initial_data = parser::parse_inital_data(&html).unwrap_or_default();
+        let que = initial_data["initialState"]["entities"]["questions"][qid].clone();
+        let answer = initial_data["initialState"]["entities"]["answers"][aid].clone();
 
-    let mut article: TimelineItem = parser::parse_entity_data(&html, "articles", &aid)
-        .unwrap_or_else(|| {
-            debug!("unable to find aritlce of {}", aid);
-            Default::default()
-        });
+        if que.is_null() {
+            return Err(Error::missing("question", qid));
+        }
+        if answer.is_null() {
+            return Err(Error::missing("answer", aid));
+        }
 
-    let author = article.author.take().unwrap();
+        let que: Question = serde_json::from_value(que)?;
+        let answer: TimelineItem = serde_json::from_value(answer)?;
+        Ok::<_, Error>((que, answer))
+    }
+    .await;
+
+    match fetched {
+        Ok((que, mut answer)) => {
+            answer.question.get_or_insert_with(|| que.clone());
+            state.archive.put(ArchiveKind::Question, qid, "", &que);
+            state.archive.put(ArchiveKind::Answer, aid, qid, &answer);
+            Ok(AnswerPage {
+                question: que,
+                answer,
+                archived_at: None,
+            })
+        }
+        Err(err) => match state.archive.get::<TimelineItem>(ArchiveKind::Answer, aid) {
+            Some(archived) => {
+                warn!("serve archived answer {}: {}", aid, err);
+                let que = state
+                    .archive
+                    .get::<Question>(ArchiveKind::Question, qid)
+                    .map(|q| q.value)
+                    .or_else(|| archived.value.question.clone())
+                    .unwrap_or_else(|| Question {
+                        id: qid.to_string(),
+                        ..Default::default()
+                    });
+                Ok(AnswerPage {
+                    question: que,
+                    answer: archived.value,
+                    archived_at: Some(archived.archived_at),
+                })
+            }
+            None => Err(err),
+        },
+    }
+}
+
+pub async fn article(state: State<AppState>, aid: Path<(String,)>) -> Result<Markup, Error> {
+    let ArticlePage {
+        mut article,
+        archived_at,
+    } = load_article(&state, &aid.0 .0).await?;
+
+    let author = article.author.take().unwrap_or_default();
 
     let title = article.title.clone().unwrap_or_default();
 
     Ok(layout(
         html! {
+            (views::archived(archived_at))
             div class="p-4 mb-2 bg-white" {
                 @if !article.image_url.is_empty() {
-                    img class="w-full mb-4" src=(article.image_url) alt=(title);
+                    img class="w-full mb-4" src=(views::image_url(&article.image_url)) alt=(title);
                 }
                 h2 class="text-xl font-bold" {(title)}
+                @if let Some(column) = article.column.as_ref().filter(|c| !c.id.is_empty()) {
+                    a class="inline-block mt-2 text-sm text-blue-600 underline" href=(format!("/column/{}", column.id)) {
+                        "专栏: " (column.title)
+                    }
+                }
                 div class="flex items-center mt-4" {
-                    img class="mr-2 w-8 h-8 object-cover rounded-sm" src=(author.avatar_url) alt=(author.name);
+                    img class="mr-2 w-8 h-8 object-cover rounded-sm" src=(views::image_url(&author.avatar_url)) alt=(author.name);
                     div {
-                        div { (author.name) }
+                        a href=[views::people_href(&author)] { (author.name) }
                         div class="text-sm text-gray-600" {
                             (PreEscaped(author.headline))
                         }
@@ -237,33 +380,335 @@ pub async fn article(aid: Path<(String,)>) -> Result<Markup, Error> {
     ))
 }
 
-pub async fn root_comment(
-    aid: Path<(String,)>,
+pub async fn load_article(state: &AppState, aid: &str) -> Result<ArticlePage, Error> {
+    let fetched = async {
+        let html = state.backend.article(aid).await?;
+
+        let article: TimelineItem = parser::parse_entity_data(&html, "articles", aid)
+            .ok_or_else(|| Error::missing("article", aid))?;
+        Ok::<_, Error>(article)
+    }
+    .await;
+
+    match fetched {
+        Ok(article) => {
+            state.archive.put(ArchiveKind::Article, aid, "", &article);
+            Ok(ArticlePage {
+                article,
+                archived_at: None,
+            })
+        }
+        Err(err) => match state.archive.get::<TimelineItem>(ArchiveKind::Article, aid) {
+            Some(archived) => {
+                warn!("serve archived article {}: {}", aid, err);
+                Ok(ArticlePage {
+                    article: archived.value,
+                    archived_at: Some(archived.archived_at),
+                })
+            }
+            None => Err(err),
+        },
+    }
+}
+
+pub async fn pin(state: State<AppState>, id: Path<(String,)>) -> Result<Markup, Error> {
+    let PinPage { pin, archived_at } = load_pin(&state, &id.0 .0).await?;
+
+    let title = match &pin.author {
+        Some(author) => format!("{} 的想法", author.name),
+        None => "想法".to_string(),
+    };
+
+    Ok(layout(
+        html! {
+            (views::archived(archived_at))
+            (views::pin(&pin))
+        },
+        Some(&title),
+    ))
+}
+
+pub async fn load_pin(state: &AppState, id: &str) -> Result<PinPage, Error> {
+    let fetched = async {
+        let response = state.backend.pin(id).await?;
+        Ok::<_, Error>(serde_json::from_value::<Pin>(response)?)
+    }
+    .await;
+
+    match fetched {
+        Ok(pin) => {
+            state.archive.put(ArchiveKind::Pin, id, "", &pin);
+            Ok(PinPage {
+                pin,
+                archived_at: None,
+            })
+        }
+        Err(err) => match state.archive.get::<Pin>(ArchiveKind::Pin, id) {
+            Some(archived) => {
+                warn!("serve archived pin {}: {}", id, err);
+                Ok(PinPage {
+                    pin: archived.value,
+                    archived_at: Some(archived.archived_at),
+                })
+            }
+            None => Err(err),
+        },
+    }
+}
+
+pub async fn zvideo(state: State<AppState>, id: Path<(String,)>) -> Result<Markup, Error> {
+    let ZVideoPage {
+        zvideo,
+        archived_at,
+    } = load_zvideo(&state, &id.0 .0).await?;
+
+    let mut item = parser::zvideo_item(zvideo);
+    let author = item.author.take().unwrap_or_default();
+    let title = item.title.clone().unwrap_or_default();
+
+    Ok(layout(
+        html! {
+            (views::archived(archived_at))
+            div class="p-4 mb-2 bg-white" {
+                h2 class="text-xl font-bold" {(title)}
+                div class="flex items-center mt-4" {
+                    img class="mr-2 w-8 h-8 object-cover rounded-sm" src=(views::image_url(&author.avatar_url)) alt=(author.name);
+                    div {
+                        a href=[views::people_href(&author)] { (author.name) }
+                        div class="text-sm text-gray-600" {
+                            (PreEscaped(author.headline))
+                        }
+                    }
+                }
+                @if let Some(created_time) = item.created_time {
+                    div class="text-gray-500 mt-4 text-sm" {
+                        "发布于 " (views::time(created_time))
+                    }
+                }
+            }
+            (views::answer(&item, true))
+        },
+        Some(&format!("视频: {}", title)),
+    ))
+}
+
+pub async fn load_zvideo(state: &AppState, id: &str) -> Result<ZVideoPage, Error> {
+    let fetched = async {
+        let response = state.backend.zvideo(id).await?;
+        let mut zvideo: ZVideo = serde_json::from_value(response)?;
+        resolve_playlist(state, &mut zvideo).await;
+        Ok::<_, Error>(zvideo)
+    }
+    .await;
+
+    match fetched {
+        Ok(zvideo) => {
+            state.archive.put(ArchiveKind::ZVideo, id, "", &zvideo);
+            Ok(ZVideoPage {
+                zvideo,
+                archived_at: None,
+            })
+        }
+        Err(err) => match state.archive.get::<ZVideo>(ArchiveKind::ZVideo, id) {
+            Some(archived) => {
+                warn!("serve archived zvideo {}: {}", id, err);
+                Ok(ZVideoPage {
+                    zvideo: archived.value,
+                    archived_at: Some(archived.archived_at),
+                })
+            }
+            None => Err(err),
+        },
+    }
+}
+
+/// Zhihu leaves the play urls out of some videos, the lens api has them.
+/// The video is still shown without them, so failures are only logged.
+async fn resolve_playlist(state: &AppState, zvideo: &mut ZVideo) {
+    let video = &mut zvideo.video;
+    if !video.playlist.is_empty() || video.video_id.is_empty() {
+        return;
+    }
+
+    let playlist = async {
+        let response = state.backend.video_playlist(&video.video_id).await?;
+        Ok::<VideoUrls, Error>(serde_json::from_value(response["playlist"].clone())?)
+    }
+    .await;
+    match playlist {
+        Ok(playlist) => video.playlist = playlist,
+        Err(err) => warn!("resolve playlist of zvideo {}: {}", zvideo.id, err),
+    }
+}
+
+pub async fn column(
+    state: State<AppState>,
+    id: Path<(String,)>,
     query: Query<Value>,
     uri: Uri,
 ) -> Result<Markup, Error> {
-    let aid = aid.0 .0;
-    let query = query.0;
+    let ColumnPage {
+        column,
+        articles: results,
+    } = load_column(&state, &id.0 .0, query.0).await?;
 
-    let type_ = query["type"].as_str().unwrap_or("answers");
+    Ok(layout(
+        html! {
+            (views::column(&column))
 
-    let results: Value = CLIENT
-        .get(format!(
-            "https://www.zhihu.com/api/v4/comment_v5/{}/{}/root_comment",
-            type_, aid
-        ))
-        .query(&query)
-        .send()
-        .await?
-        .json()
-        .await?;
+            (render_prev(&results.paging, uri.path(), html! {
+                div class="p-4 mb-2 bg-white text-center font-bold" { "查看上一页" }
+            }))
 
-    let paging: Paging = serde_json::from_value(results["paging"].clone())?;
-    let data: Vec<Comment> = serde_json::from_value(results["data"].clone())?;
+            @if results.data.is_empty() {
+                div class="p-4 mb-2 bg-white text-center font-bold" { "暂无数据"}
+            }
+            @for item in &results.data {
+                (views::timeline(item))
+            }
+
+            (render_next(&results.paging, uri.path(), html! {
+                div class="p-4 my-2 bg-white text-center font-base" { "查看下一页" }
+            }))
+        },
+        Some(&format!("专栏: {}", column.title)),
+    ))
+}
+
+pub async fn load_column(
+    state: &AppState,
+    id: &str,
+    mut query: Value,
+) -> Result<ColumnPage, Error> {
+    if !query["limit"].is_string() {
+        query["limit"] = json!("10");
+    }
+
+    let column: Column = serde_json::from_value(state.backend.column(id).await?)?;
+    let items = state.backend.column_items(id, &query).await?;
+
+    Ok(ColumnPage {
+        column,
+        articles: parser::parse_list(&items),
+    })
+}
+
+/// Tabs of a profile page, the zhihu list and ours.
+pub const PEOPLE_TABS: [(&str, &str); 3] =
+    [("answers", "回答"), ("articles", "文章"), ("pins", "想法")];
+
+pub async fn people(
+    state: State<AppState>,
+    url_token: Path<(String,)>,
+    query: Query<Value>,
+    uri: Uri,
+) -> Result<Markup, Error> {
+    let url_token = url_token.0 .0;
+    render_people(&state, &url_token, "answers", query.0, uri).await
+}
+
+pub async fn people_tab(
+    state: State<AppState>,
+    p: Path<(String, String)>,
+    query: Query<Value>,
+    uri: Uri,
+) -> Result<Markup, Error> {
+    let (url_token, tab) = p.0;
+    render_people(&state, &url_token, &tab, query.0, uri).await
+}
+
+async fn render_people(
+    state: &AppState,
+    url_token: &str,
+    tab: &str,
+    query: Value,
+    uri: Uri,
+) -> Result<Markup, Error> {
+    let PeoplePage {
+        people,
+        tab,
+        items: results,
+    } = load_people(state, url_token, tab, query).await?;
+
+    Ok(layout(
+        html! {
+            (views::people(&people))
+
+            div class="p-4 mb-2 bg-white text-sm" {
+                @for (name, label) in PEOPLE_TABS {
+                    @if name == tab {
+                        span class="inline-block mr-2 px-2 py-1 rounded bg-gray-200 font-bold" { (label) }
+                    } @else {
+                        a class="inline-block mr-2 px-2 py-1 rounded text-gray-500" href=(format!("/people/{}/{}", url_token, name)) { (label) }
+                    }
+                }
+            }
+
+            (render_prev(&results.paging, uri.path(), html! {
+                div class="p-4 mb-2 bg-white text-center font-bold" { "查看上一页" }
+            }))
+
+            @if results.data.is_empty() {
+                div class="p-4 mb-2 bg-white text-center font-bold" { "暂无数据"}
+            }
+            @for item in &results.data {
+                (views::timeline(item))
+            }
+
+            (render_next(&results.paging, uri.path(), html! {
+                div class="p-4 my-2 bg-white text-center font-base" { "查看下一页" }
+            }))
+        },
+        Some(&format!("{} 的主页", people.name)),
+    ))
+}
+
+/// Unknown tabs are not found rather than sent to zhihu.
+pub async fn load_people(
+    state: &AppState,
+    url_token: &str,
+    tab: &str,
+    mut query: Value,
+) -> Result<PeoplePage, Error> {
+    if !PEOPLE_TABS.iter().any(|(name, _)| *name == tab) {
+        return Err(Error::NotFound);
+    }
+    if !query["include"].is_string() {
+        query["include"] = json!(match tab {
+            "pins" => "data[*].content,comment_count,like_count,reaction_count,created,updated",
+            _ => "data[*].excerpt,voteup_count,comment_count,created_time,updated_time,question,image_url",
+        });
+    }
+    if !query["limit"].is_string() {
+        query["limit"] = json!("20");
+    }
+
+    let people: People = serde_json::from_value(state.backend.member(url_token).await?)?;
+    let items = state.backend.member_items(url_token, tab, &query).await?;
+
+    Ok(PeoplePage {
+        people,
+        tab: tab.to_string(),
+        items: parser::parse_list(&items),
+    })
+}
+
+pub async fn root_comment(
+    state: State<AppState>,
+    aid: Path<(String,)>,
+    query: Query<Value>,
+    uri: Uri,
+) -> Result<Markup, Error> {
+    let CommentsPage {
+        comments: ApiResults { data, paging },
+        archived_at,
+        ..
+    } = load_root_comment(&state, &aid.0 .0, query.0).await?;
 
     Ok(layout(
         html! {
-            (render_prev(&paging, &uri.path(), html! {
+            (views::archived(archived_at))
+            (render_prev(&paging, uri.path(), html! {
                 div class="p-4 mb-2 bg-white text-center font-bold" { "查看上一页" }
             }))
 
@@ -275,7 +720,7 @@ pub async fn root_comment(
                 }
             }
 
-            (render_next(&paging, &uri.path(), html! {
+            (render_next(&paging, uri.path(), html! {
                 div class="p-4 my-2 bg-white text-center font-base" { "查看下一页" }
             }))
         },
@@ -283,31 +728,73 @@ pub async fn root_comment(
     ))
 }
 
+pub async fn load_root_comment(
+    state: &AppState,
+    aid: &str,
+    query: Value,
+) -> Result<CommentsPage, Error> {
+    let type_ = query["type"].as_str().unwrap_or("answers");
+
+    let parent = format!("{}/{}", type_, aid);
+
+    let fetched = async {
+        let results = state.backend.root_comment(type_, aid, &query).await?;
+
+        let paging: Paging = serde_json::from_value(results["paging"].clone())?;
+        let data: Vec<Comment> = serde_json::from_value(results["data"].clone())?;
+        Ok::<_, Error>(ApiResults { data, paging })
+    }
+    .await;
+
+    match fetched {
+        Ok(comments) => {
+            for comment in &comments.data {
+                state
+                    .archive
+                    .put(ArchiveKind::Comment, &comment.id, &parent, comment);
+            }
+            Ok(CommentsPage {
+                root: None,
+                comments,
+                archived_at: None,
+            })
+        }
+        Err(err) => {
+            let archived: Vec<_> = state.archive.children(ArchiveKind::Comment, &parent);
+            let archived_at = archived.iter().map(|c| c.archived_at).min();
+            if archived_at.is_none() {
+                return Err(err);
+            }
+            warn!("serve archived comments of {}: {}", parent, err);
+            let data = archived.into_iter().map(|c| c.value).collect();
+            Ok(CommentsPage {
+                root: None,
+                comments: ApiResults {
+                    data,
+                    paging: Paging::default(),
+                },
+                archived_at,
+            })
+        }
+    }
+}
+
 pub async fn child_comment(
+    state: State<AppState>,
     cid: Path<(String,)>,
     query: Query<Value>,
     uri: Uri,
 ) -> Result<Markup, Error> {
-    let cid = cid.0 .0;
-    let query = query.0;
-
-    let results: Value = CLIENT
-        .get(format!(
-            "https://www.zhihu.com/api/v4/comment_v5/comment/{}/child_comment",
-            cid
-        ))
-        .query(&query)
-        .send()
-        .await?
-        .json()
-        .await?;
-
-    let paging: Paging = serde_json::from_value(results["paging"].clone())?;
-    let data: Vec<Comment> = serde_json::from_value(results["data"].clone())?;
-    let root: Comment = serde_json::from_value(results["root"].clone())?;
+    let CommentsPage {
+        root,
+        comments: ApiResults { data, paging },
+        archived_at,
+    } = load_child_comment(&state, &cid.0 .0, query.0).await?;
+    let root = root.unwrap_or_default();
 
     Ok(layout(
         html! {
+            (views::archived(archived_at))
             div class="p-4 mb-2 bg-white" {
                 (views::comment(&root, false))
             }
@@ -315,7 +802,7 @@ pub async fn child_comment(
 
 
             ul class="p-4" {
-                (render_prev(&paging, &uri.path(), html! {
+                (render_prev(&paging, uri.path(), html! {
                     div class="p-4 mb-2 bg-white text-center font-bold" { "查看上一页" }
                 }))
 
@@ -325,7 +812,7 @@ pub async fn child_comment(
                     }
                 }
 
-                (render_next(&paging, &uri.path(), html! {
+                (render_next(&paging, uri.path(), html! {
                     div class="p-4 my-2 bg-white text-center font-base" { "查看下一页" }
                 }))
             }
@@ -334,30 +821,80 @@ pub async fn child_comment(
     ))
 }
 
-pub async fn search(query: Query<Value>) -> Result<Markup, Error> {
+pub async fn load_child_comment(
+    state: &AppState,
+    cid: &str,
+    query: Value,
+) -> Result<CommentsPage, Error> {
+    let parent = format!("comment/{}", cid);
+
+    let fetched = async {
+        let results = state.backend.child_comment(cid, &query).await?;
+
+        let paging: Paging = serde_json::from_value(results["paging"].clone())?;
+        let data: Vec<Comment> = serde_json::from_value(results["data"].clone())?;
+        let root: Comment = serde_json::from_value(results["root"].clone())?;
+        Ok::<_, Error>((root, ApiResults { data, paging }))
+    }
+    .await;
+
+    match fetched {
+        Ok((root, comments)) => {
+            // the root belongs to the list of its answer or article, keep it there once archived
+            if state
+                .archive
+                .get::<Comment>(ArchiveKind::Comment, cid)
+                .is_none()
+            {
+                state.archive.put(ArchiveKind::Comment, cid, "", &root);
+            }
+            for comment in &comments.data {
+                state
+                    .archive
+                    .put(ArchiveKind::Comment, &comment.id, &parent, comment);
+            }
+            Ok(CommentsPage {
+                root: Some(root),
+                comments,
+                archived_at: None,
+            })
+        }
+        Err(err) => {
+            let root = state.archive.get::<Comment>(ArchiveKind::Comment, cid);
+            let archived: Vec<_> = state.archive.children(ArchiveKind::Comment, &parent);
+            let archived_at = archived
+                .iter()
+                .map(|c| c.archived_at)
+                .chain(root.as_ref().map(|r| r.archived_at))
+                .min();
+            if archived_at.is_none() {
+                return Err(err);
+            }
+            warn!("serve archived comments of {}: {}", parent, err);
+            let data = archived.into_iter().map(|c| c.value).collect();
+            Ok(CommentsPage {
+                root: root.map(|r| r.value),
+                comments: ApiResults {
+                    data,
+                    paging: Paging::default(),
+                },
+                archived_at,
+            })
+        }
+    }
+}
+
+pub async fn search(state: State<AppState>, query: Query<Value>) -> Result<Markup, Error> {
     let query = query.0;
     let q = query["q"].as_str().unwrap_or_default();
 
-    let results = if !q.is_empty() {
-        let response: Value = CLIENT
-            .get("https://www.zhihu.com/api/v4/search_v3")
-            .query(&query)
-            .header("x-zse-93", "101_3_3.0")
-            .header("x-zse-96", "2.0_")
-            .send()
-            .await?
-            .json()
-            .await?;
-        parser::parse_search(&response)
-    } else {
-        Default::default()
-    };
+    let results = load_search(&state, &query).await?;
 
     Ok(layout(
         html! {
             div class="p-4 mb-2 bg-white" {
                 form class="flex mb-0" action="/search" {
-                    @for (name, val) in [("type", "content"), ("limit", "20"), ("show_all_topics", "1")] {
+                    @for (name, val) in [("type", "content".to_string()), ("limit", config::ui().search_limit.to_string()), ("show_all_topics", "1".to_string())] {
                         input class="hidden" type="text" name=(name) value=(val);
                     }
                     input placeholder="请输入搜索内容" class="h-8 border border-gray-200 px-1" type="search" name="q" value=(q) autocomplete="off";
@@ -394,17 +931,166 @@ pub async fn search(query: Query<Value>) -> Result<Markup, Error> {
     ))
 }
 
+/// Searches only when the query has a non empty `q`.
+pub async fn load_search(state: &AppState, query: &Value) -> Result<ApiResults<SearchItem>, Error> {
+    if query["q"].as_str().unwrap_or_default().is_empty() {
+        return Ok(Default::default());
+    }
+
+    let response = state.backend.search(query).await?;
+    Ok(parser::parse_search(&response))
+}
+
+pub async fn status(state: State<AppState>) -> Markup {
+    let health = health::current();
+    let health_state = match health.state {
+        HealthState::Unknown => "检查中",
+        HealthState::Valid => "正常",
+        HealthState::Invalid => "失效",
+        HealthState::Throttled => "被知乎限流",
+        HealthState::Unreachable => "无法访问知乎",
+    };
+
+    layout(
+        html! {
+            div class="p-4 mb-2 bg-white" {
+                h2 class="text-lg font-bold" { "状态" }
+                dl class="mt-2 grid grid-cols-2 text-sm" {
+                    dt class="text-gray-500" { "知乎 cookie" }
+                    dd { (health_state) }
+                    dt class="text-gray-500" { "说明" }
+                    dd { (health.message) }
+                    dt class="text-gray-500" { "检查时间" }
+                    dd {
+                        @if let Some(checked_at) = health.checked_at {
+                            (views::datetime(checked_at))
+                        } @else {
+                            "-"
+                        }
+                    }
+                }
+            }
+            div class="p-4 mb-2 bg-white" {
+                h2 class="text-lg font-bold" { "Cookie 池" }
+                table class="mt-2 w-full text-sm text-left" {
+                    tr class="text-gray-500" {
+                        th { "cookie" } th { "成功" } th { "失败" } th { "状态" }
+                    }
+                    @for stats in state.cookies.stats() {
+                        tr {
+                            td class="font-mono" { (stats.label) }
+                            td { (stats.success) }
+                            td { (stats.failure) }
+                            td {
+                                @if let Some(secs) = stats.benched_for {
+                                    (secs) " 秒后恢复"
+                                } @else {
+                                    "可用"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        Some("状态"),
+    )
+}
+
 pub fn error(status: StatusCode, err: &Error) -> Markup {
+    let (title, explain) = match err.cause() {
+        Error::NotFound => (
+            "内容不存在",
+            "该问题, 回答或文章可能已被作者删除或被知乎屏蔽.",
+        ),
+        Error::Unauthorized(_) => (
+            "知乎登录失效",
+            "知乎拒绝了当前的 cookie, 请检查 ZHIHU_COOKIE 是否正确或已过期.",
+        ),
+        Error::AntiCrawler => (
+            "触发了知乎反爬验证",
+            "知乎认为当前请求存在异常, 请稍后再试, 或在浏览器中登录知乎完成验证后更新 cookie.",
+        ),
+        Error::RateLimited => ("请求过于频繁", "知乎限制了请求频率, 请稍后再试."),
+        Error::Busy => (
+            "服务繁忙",
+            "为了避免触发知乎的反爬限制, 当前排队的请求过多, 请稍等几秒后刷新页面重试.",
+        ),
+        Error::MissingEntity { .. } => (
+            "页面中没有找到内容",
+            "知乎返回的页面中没有需要的数据, 内容可能已被删除, 或需要登录才能查看.",
+        ),
+        Error::Upstream(_) | Error::Reqwest(_) => ("无法访问知乎", "请求知乎时出错, 请稍后再试."),
+        Error::Json(_) | Error::Any(_) => ("出错了", "处理知乎返回的数据时出错."),
+        Error::Retried { .. } => unreachable!("cause() unwraps retries"),
+    };
+
+    let retries = match err {
+        Error::Retried { retries, .. } => Some(retries),
+        _ => None,
+    };
+
     layout(
         html! {
             div class="p-4 mb-2 bg-white" {
-                p { (format!("{}: {}", status, err)) }
+                h2 class="text-lg font-bold" { (title) }
+                p class="mt-2" { (explain) }
+                @if let Some(retries) = retries {
+                    p class="mt-2 text-sm" { "已自动重试 " (retries) " 次." }
+                }
+                p class="mt-2 text-sm text-gray-400" { (format!("{}: {}", status, err)) }
             }
         },
-        Some("Error"),
+        Some(title),
     )
 }
 
+/// `?nojs=1` switches to pages that work without javascript and remembers the
+/// choice in a cookie, `?nojs=0` switches back. The parameter is not passed on
+/// to the handlers.
+pub async fn no_js<B>(mut request: Request<B>, next: Next<B>) -> Response {
+    let query = request.uri().query().unwrap_or_default().to_string();
+    let (params, rest): (Vec<&str>, Vec<&str>) = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .partition(|pair| pair.split('=').next() == Some("nojs"));
+    let param = params
+        .last()
+        .map(|pair| !matches!(pair.split_once('='), Some((_, "0" | "false"))));
+
+    if param.is_some() {
+        let path = request.uri().path();
+        let uri = match rest.is_empty() {
+            true => path.to_string(),
+            false => format!("{}?{}", path, rest.join("&")),
+        };
+        if let Ok(uri) = uri.parse() {
+            *request.uri_mut() = uri;
+        }
+    }
+
+    let cookie = request
+        .headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .any(|cookie| cookie.trim() == "nojs=1");
+    let enabled = param.unwrap_or(cookie);
+
+    let mut response = views::NO_JS.scope(enabled, next.run(request)).await;
+    if let Some(enabled) = param {
+        let cookie = match enabled {
+            true => "nojs=1; Path=/; Max-Age=31536000; SameSite=Lax",
+            false => "nojs=; Path=/; Max-Age=0; SameSite=Lax",
+        };
+        response
+            .headers_mut()
+            .append(header::SET_COOKIE, HeaderValue::from_static(cookie));
+    }
+    response
+}
+
 pub async fn default(uri: Uri) -> Response {
     let path = uri.path();
 
@@ -435,7 +1121,9 @@ pub async fn default(uri: Uri) -> Response {
 }
 
 fn layout(body: Markup, title: Option<&str>) -> Markup {
-    let title = title.unwrap_or("Light Zhihu - 一个轻量知乎客户端");
+    let ui = config::ui();
+    let default_title = format!("{} - 一个轻量知乎客户端", ui.title);
+    let title = title.unwrap_or(&default_title);
 
     html! {
         head {
@@ -443,54 +1131,31 @@ fn layout(body: Markup, title: Option<&str>) -> Markup {
             meta charset="utf-8";
             meta name="viewport" content="width=device-width, initial-scale=1.0";
             link rel="shortcut icon" href="/favicon.png" type="image/png";
-            script src="https://cdn.jsdelivr.net/npm/petite-vue@0.4.1/dist/petite-vue.iife.min.js" defer init {}
-            script src="https://cdn.twind.style" crossorigin {}
-            script {
-                (PreEscaped(r#"
-                twind.install({
-                    hash: false,
-                    presets: [
-                      {
-                        rules: [
-                          ['line-clamp-none', { '-webkit-line-clamp': 'unset' }],
-                          [
-                            'line-clamp-(\\d+)',
-                            ({ 1: _ }) => lineClamp(_),
-                          ],
-                        ]
-                      }
-                    ]
-                  })
-                  
-                  function lineClamp(lines) {
-                    return {
-                      overflow: 'hidden',
-                      display: '-webkit-box',
-                      '-webkit-box-orient': 'vertical',
-                      '-webkit-line-clamp': `${lines}`,
-                    }
-                  }
-                "#))
+            link rel="stylesheet" href=(assets::href("style.css"));
+            @if !views::no_js() {
+                script src=(assets::href("app.js")) defer {}
             }
         }
 
         body class="min-h-screen text-base bg-gray-100" {
             header class="bg-white mb-2 px-2 py-4 flex justify-center" {
                 nav class="flex flex-grow max-w-2xl" {
-                    a class="font-bold text-gray-500 mr-auto" href="/" { "Light Zhihu" }
+                    a class="font-bold text-gray-500 mr-auto" href="/" { (ui.title) }
                     a class="ml-2 underline" href="/recommend" { "推荐" }
+                    a class="ml-2 underline" href="/hot" { "热榜" }
                     a class="ml-2 underline" href="/search" { "搜索" }
                 }
             }
         }
 
         main class="max-w-2xl mx-auto" {
+            (views::health_banner(&health::current()))
             (body)
         }
     }
 }
 
-fn paging_href(href: &str, path: &str) -> String {
+pub fn paging_href(href: &str, path: &str) -> String {
     if let Ok(url) = Uri::from_str(href) {
         let query = url.query().unwrap_or_default();
 
@@ -15,16 +15,64 @@ pub enum Error {
     Reqwest(#[from] reqwest::Error),
     #[error(transparent)]
     Any(#[from] anyhow::Error),
+    #[error("upstream content not found")]
+    NotFound,
+    #[error("zhihu rejected the cookie with status {0}")]
+    Unauthorized(u16),
+    #[error("zhihu anti-crawler challenge")]
+    AntiCrawler,
+    #[error("zhihu rate limited the request")]
+    RateLimited,
+    #[error("{entity} {id} missing in js-initialData")]
+    MissingEntity { entity: String, id: String },
+    #[error("upstream responded with status {0}")]
+    Upstream(u16),
+    #[error("too many upstream requests queued, try again later")]
+    Busy,
+    #[error("{last} (retried {retries} times)")]
+    Retried { retries: u32, last: Box<Error> },
+}
+
+impl Error {
+    pub fn missing(entity: &str, id: &str) -> Self {
+        Error::MissingEntity {
+            entity: entity.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    /// The underlying error, skipping retry bookkeeping.
+    pub fn cause(&self) -> &Error {
+        match self {
+            Error::Retried { last, .. } => last.cause(),
+            err => err,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Retried { last, .. } => last.status(),
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::Unauthorized(_) => StatusCode::FORBIDDEN,
+            Error::AntiCrawler | Error::Busy => StatusCode::SERVICE_UNAVAILABLE,
+            Error::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Error::MissingEntity { .. } | Error::Upstream(_) => StatusCode::BAD_GATEWAY,
+            Error::Reqwest(err) if err.is_timeout() => StatusCode::GATEWAY_TIMEOUT,
+            Error::Reqwest(_) => StatusCode::BAD_GATEWAY,
+            Error::Json(_) | Error::Any(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         error!("{:?}", self);
-        routes::error(StatusCode::INTERNAL_SERVER_ERROR, &self).into_response()
+        let status = self.status();
+        (status, routes::error(status, &self)).into_response()
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Paging {
     pub is_end: Option<bool>,
@@ -34,14 +82,69 @@ pub struct Paging {
     pub totals: u32,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ApiResults<T> {
     pub data: Vec<T>,
     pub paging: Paging,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
+pub struct QuestionPage {
+    pub question: Question,
+    pub answers: ApiResults<TimelineItem>,
+    /// Set when served from the archive.
+    pub archived_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnswerPage {
+    pub question: Question,
+    pub answer: TimelineItem,
+    pub archived_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArticlePage {
+    pub article: TimelineItem,
+    pub archived_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PinPage {
+    pub pin: Pin,
+    pub archived_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZVideoPage {
+    pub zvideo: ZVideo,
+    pub archived_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PeoplePage {
+    pub people: People,
+    /// `answers`, `articles` or `pins`.
+    pub tab: String,
+    pub items: ApiResults<TimelineItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnPage {
+    pub column: Column,
+    pub articles: ApiResults<TimelineItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommentsPage {
+    /// The comment being replied to, for child comments.
+    pub root: Option<Comment>,
+    pub comments: ApiResults<Comment>,
+    pub archived_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Question {
     #[serde(deserialize_with = "as_string")]
@@ -58,15 +161,29 @@ pub struct Question {
     pub voteup_count: u64,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum SearchItem {
     #[default]
     Unknown,
     RelevantQuery(Value),
-    SearchResult(TimelineItem),
+    SearchResult(Box<TimelineItem>),
+}
+
+/// An entry of the hot list, always a question.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotItem {
+    /// 1 based position in the list.
+    pub rank: usize,
+    /// Like `1234 万热度`.
+    pub heat: String,
+    pub question: Question,
+    pub excerpt: String,
+    pub thumbnail: Option<String>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct TimelineItem {
     #[serde(deserialize_with = "as_string")]
@@ -84,7 +201,7 @@ pub struct TimelineItem {
     pub question: Option<Question>,
     pub author: Option<Author>,
     pub attachment: Option<Attachment>,
-    #[serde(alias = "createdTime")]
+    #[serde(alias = "createdTime", alias = "created")]
     pub created_time: Option<i64>,
     #[serde(alias = "updatedTime", alias = "updated")]
     pub updated_time: Option<i64>,
@@ -92,21 +209,187 @@ pub struct TimelineItem {
     pub voteup_count: u64,
     #[serde(alias = "commentCount")]
     pub comment_count: u64,
+    /// Set for pins, whose `content` is built from it.
+    pub pin: Option<Box<Pin>>,
+    /// The column an article was published in.
+    pub column: Option<Column>,
 
-    #[serde(flatten)]
+    /// Everything else zhihu sent, not part of the json api.
+    #[serde(flatten, skip_serializing)]
     pub ext: Value,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// A "想法", short text with up to nine images, possibly reposting another pin.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pin {
+    #[serde(deserialize_with = "as_string")]
+    pub id: String,
+    pub author: Option<Author>,
+    pub content: Vec<PinContent>,
+    pub created: Option<i64>,
+    pub updated: Option<i64>,
+    pub comment_count: u64,
+    pub like_count: u64,
+    /// Newer responses count likes as reactions.
+    pub reaction_count: u64,
+    pub repin_count: u64,
+    /// The pin this one reposts.
+    pub origin_pin: Option<Box<Pin>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PinContent {
+    Text {
+        #[serde(default)]
+        content: String,
+    },
+    Image {
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        original_url: String,
+        #[serde(default)]
+        width: u32,
+        #[serde(default)]
+        height: u32,
+    },
+    Link {
+        #[serde(default)]
+        url: String,
+        #[serde(default)]
+        title: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl Pin {
+    pub fn likes(&self) -> u64 {
+        self.like_count.max(self.reaction_count)
+    }
+
+    /// Html of the text and link blocks, images are left out.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|content| match content {
+                PinContent::Text { content } => Some(content.clone()),
+                PinContent::Link { url, title } => Some(
+                    html! {
+                        p { a href=(url) { (if title.is_empty() { url } else { title }) } }
+                    }
+                    .into_string(),
+                ),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Urls of the images, the original size when zhihu sent one.
+    pub fn images(&self) -> Vec<&str> {
+        self.content
+            .iter()
+            .filter_map(|content| match content {
+                PinContent::Image {
+                    url, original_url, ..
+                } => Some(if original_url.is_empty() {
+                    url.as_str()
+                } else {
+                    original_url.as_str()
+                }),
+                _ => None,
+            })
+            .filter(|url| !url.is_empty())
+            .collect()
+    }
+}
+
+/// A standalone video, not attached to an answer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZVideo {
+    #[serde(deserialize_with = "as_string")]
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub image_url: String,
+    pub author: Option<Author>,
+    pub video: ZVideoInfo,
+    pub play_count: u32,
+    pub voteup_count: u64,
+    pub comment_count: u64,
+    #[serde(alias = "published_at")]
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZVideoInfo {
+    pub video_id: String,
+    pub thumbnail: String,
+    /// Seconds.
+    pub duration: f64,
+    pub playlist: VideoUrls,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Author {
+    #[serde(deserialize_with = "as_string")]
+    pub id: String,
+    /// Names the profile page, empty for anonymous users.
+    #[serde(alias = "urlToken")]
+    pub url_token: String,
+    #[serde(alias = "avatarUrl")]
+    pub avatar_url: String,
+    pub name: String,
+    pub headline: String,
+}
+
+/// A "专栏", a collection of articles.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Column {
+    #[serde(deserialize_with = "as_string")]
+    pub id: String,
+    pub title: String,
+    pub intro: String,
+    pub description: String,
+    #[serde(alias = "imageUrl")]
+    pub image_url: String,
+    pub author: Option<Author>,
+    #[serde(alias = "articlesCount", alias = "items_count")]
+    pub articles_count: u64,
+    pub followers: u64,
+}
+
+/// A member's profile.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct People {
+    #[serde(deserialize_with = "as_string")]
+    pub id: String,
+    #[serde(alias = "urlToken")]
+    pub url_token: String,
     #[serde(alias = "avatarUrl")]
     pub avatar_url: String,
     pub name: String,
     pub headline: String,
+    pub description: String,
+    #[serde(alias = "followerCount")]
+    pub follower_count: u64,
+    #[serde(alias = "answerCount")]
+    pub answer_count: u64,
+    #[serde(alias = "articlesCount")]
+    pub articles_count: u64,
+    #[serde(alias = "pinsCount")]
+    pub pins_count: u64,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Attachment {
     #[serde(rename = "type")]
@@ -114,7 +397,7 @@ pub struct Attachment {
     pub video: AttachmentVideo,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AttachmentVideo {
     pub title: String,
@@ -124,28 +407,41 @@ pub struct AttachmentVideo {
     pub video_info: VideoInfo,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct VideoInfo {
     pub thumbnail: String,
     pub playlist: VideoUrls,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct VideoUrls {
+    #[serde(alias = "LD")]
     pub ld: Option<VideoUrl>,
+    #[serde(alias = "SD")]
     pub sd: Option<VideoUrl>,
+    #[serde(alias = "HD")]
     pub hd: Option<VideoUrl>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+impl VideoUrls {
+    pub fn is_empty(&self) -> bool {
+        [&self.ld, &self.sd, &self.hd]
+            .iter()
+            .all(|url| url.as_ref().is_none_or(|url| url.url.is_empty()))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct VideoUrl {
+    /// `play_url` in lens playlists.
+    #[serde(alias = "play_url")]
     pub url: String,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Comment {
     pub id: String,
@@ -161,7 +457,7 @@ pub struct Comment {
     pub author_tag: Vec<AuthorTag>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AuthorTag {
     pub text: String,
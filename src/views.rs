@@ -1,16 +1,37 @@
 use std::collections::HashMap;
 
+use chrono::{SecondsFormat, TimeZone, Utc};
 use lol_html::{element, html_content::ContentType};
 use maud::{Markup, PreEscaped};
 use once_cell::sync::OnceCell;
 use regex::Regex;
 use serde_json::Value;
 
-use crate::types::{Attachment, Comment, Question, TimelineItem};
+use crate::{
+    config,
+    health::{CookieHealth, HealthState},
+    images::ImageClient,
+    resize,
+    types::{
+        Attachment, Author, Column, Comment, HotItem, People, Pin, PinContent, Question,
+        TimelineItem,
+    },
+};
 
 static EMOJI: OnceCell<HashMap<String, String>> = OnceCell::new();
 static EMOJI_RE: OnceCell<regex::Regex> = OnceCell::new();
 static HREF_RE: OnceCell<Regex> = OnceCell::new();
+static ZVIDEO_RE: OnceCell<Regex> = OnceCell::new();
+
+tokio::task_local! {
+    /// Set for requests rendered without javascript, see `routes::no_js`.
+    pub static NO_JS: bool;
+}
+
+/// Whether the page is rendered for browsers without javascript.
+pub fn no_js() -> bool {
+    NO_JS.try_with(|no_js| *no_js).unwrap_or(false)
+}
 
 macro_rules! css {
     ($css:expr, $class:expr) => {
@@ -26,7 +47,8 @@ macro_rules! css {
     };
 }
 
-fn render_html(html: &str) -> PreEscaped<String> {
+/// Replaces emoji placeholders like `[赞同]` with `f(name, image url)`.
+pub fn replace_emoji(html: &str, f: impl Fn(&str, &str) -> String) -> String {
     let emoji =
         EMOJI.get_or_init(|| serde_json::from_str(include_str!("../data/emoji.json")).unwrap());
     let emoji_re = EMOJI_RE.get_or_init(|| {
@@ -35,15 +57,67 @@ fn render_html(html: &str) -> PreEscaped<String> {
 
         regex::Regex::new(&re).unwrap()
     });
+
+    emoji_re
+        .replace_all(html, |caps: &regex::Captures| {
+            let key = caps[0].trim_matches('[').trim_matches(']');
+            if let Some(src) = emoji.get(key) {
+                f(key, src)
+            } else {
+                key.to_string()
+            }
+        })
+        .into_owned()
+}
+
+/// Points zhimg.com images at our `/img` proxy, other urls are left alone.
+pub fn image_url(url: &str) -> String {
+    if config::ui().proxy_images && ImageClient::resolve(url).is_some() {
+        format!("/img?url={}", urlencoding::encode(url))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Resized variants of a zhimg.com image no wider than the original, plus the
+/// original itself when its width is known. `None` without the image proxy or
+/// when the original is already small.
+pub fn srcset(url: &str, raw_width: Option<u32>) -> Option<String> {
+    if !config::ui().proxy_images || ImageClient::resolve(url).is_none() {
+        return None;
+    }
+
+    let widths: Vec<u32> = resize::WIDTHS
+        .into_iter()
+        .filter(|&width| raw_width.is_none_or(|raw| width < raw))
+        .collect();
+    if widths.is_empty() {
+        return None;
+    }
+
+    let url = urlencoding::encode(url);
+    let mut srcset: Vec<String> = widths
+        .iter()
+        .map(|width| format!("/img?url={}&w={} {}w", url, width, width))
+        .collect();
+    // browsers only pick from the listed candidates, keep full resolution available
+    if let Some(raw) = raw_width {
+        srcset.push(format!("/img?url={} {}w", url, raw));
+    }
+    Some(srcset.join(", "))
+}
+
+pub fn render_html(html: &str) -> PreEscaped<String> {
     let href_re = HREF_RE.get_or_init(|| Regex::new(r#"https?://(.*?).zhihu.com/(.*)"#).unwrap());
+    // video links, also behind link.zhihu.com or without the www, drop their query
+    let zvideo_re = ZVIDEO_RE.get_or_init(|| {
+        Regex::new(r#"^(?:(?:https?:)?//(?:www\.)?zhihu\.com)?/zvideo/(\d+)"#).unwrap()
+    });
 
-    let html = emoji_re.replace_all(html, |caps: &regex::Captures| {
-        let key = caps[0].trim_matches('[').trim_matches(']');
-        if let Some(src) = emoji.get(key) {
-            format!(r#"<img class="w-5 h-5 !my-0 mx-1 inline-block align-text-bottom" src="{src}"></img>"#)
-        } else {
-            key.to_string()
-        }
+    let html = replace_emoji(html, |_, src| {
+        format!(
+            r#"<img class="w-5 h-5 !my-0 mx-1 inline-block align-text-bottom" src="{src}"></img>"#
+        )
     });
 
     let html = lol_html::rewrite_str(
@@ -80,6 +154,13 @@ fn render_html(html: &str) -> PreEscaped<String> {
                             format!("/{path}")
                         }
                     });
+                    let href = match zvideo_re.captures(&href) {
+                        Some(caps) => {
+                            a.set_attribute("class", "text-blue-600 underline").ok();
+                            format!("/zvideo/{}", &caps[1])
+                        }
+                        None => href.into_owned(),
+                    };
 
                     a.set_attribute("href", &href).ok();
                     Ok(())
@@ -96,10 +177,19 @@ fn render_html(html: &str) -> PreEscaped<String> {
                     let img_src = actual_src.or(original_src.clone()).unwrap_or_default();
                     img.set_attribute("src", &img_src).ok();
 
+                    let raw_width = img
+                        .get_attribute("data-rawwidth")
+                        .and_then(|w| w.parse().ok());
+                    let source = original_src.as_deref().unwrap_or(&img_src);
+                    if let Some(srcset) = srcset(source, raw_width) {
+                        img.set_attribute("srcset", &srcset).ok();
+                        img.set_attribute("sizes", resize::SIZES).ok();
+                    }
+
                     img.before(
                         &format!(
                             "<a target=\"_blank\" href=\"{}\">",
-                            original_src.unwrap_or(img_src)
+                            image_url(&original_src.unwrap_or(img_src))
                         ),
                         ContentType::Html,
                     );
@@ -113,7 +203,8 @@ fn render_html(html: &str) -> PreEscaped<String> {
                         let aclass = a.get_attribute("class").unwrap_or_default();
                         let src = a.get_attribute("href").unwrap_or_default();
 
-                        let img = format!(r#"<img loading="lazy" src={src} #width# />"#);
+                        let src = image_url(&src);
+                        let img = format!(r#"<img loading="lazy" src="{src}" #width# />"#);
                         let img = if aclass == "comment_sticker" {
                             img.replace("#width#", "width=\"80\"")
                         } else {
@@ -133,7 +224,7 @@ fn render_html(html: &str) -> PreEscaped<String> {
 
                     a.append(
                         &if let Some(img_src) = a.get_attribute("data-image") {
-                            format!(r#"</div><img class="w-16 h-16 object-cover rounded ml-1" loading="lazy" src="{}" />"#, img_src)
+                            format!(r#"</div><img class="w-16 h-16 object-cover rounded ml-1" loading="lazy" src="{}" />"#, image_url(&img_src))
                         } else {
                             "</div>".to_string()
                         },
@@ -141,6 +232,12 @@ fn render_html(html: &str) -> PreEscaped<String> {
                     );
                     Ok(())
                 }),
+                // after `figure img`, which picks the src
+                element!("img[src]", |img| {
+                    let src = img.get_attribute("src").unwrap_or_default();
+                    img.set_attribute("src", &image_url(&src)).ok();
+                    Ok(())
+                }),
             ],
             ..Default::default()
         },
@@ -158,7 +255,7 @@ fn attachment(attachment: &Option<Attachment>) -> Markup {
             let hd_url = info.playlist.hd.map(|v| v.url).or(sd_url.clone());
             return html! {
                 div  {
-                    video controls loading="lazy" poster=(info.thumbnail) src=[sd_url];
+                    video controls loading="lazy" poster=(image_url(&info.thumbnail)) src=[sd_url];
                     a href=[hd_url] {
                         div class="p-2 bg-gray-200 rounded-b-sm" {
                             h3 class="text-sm" { (video.title) }
@@ -182,29 +279,45 @@ pub fn answer(answer: &TimelineItem, show_all: bool) -> Markup {
         .or(answer.excerpt.as_ref())
         .map(String::as_str)
         .unwrap_or_default();
+    let no_js = no_js();
+    let toggle = format!("expand-answer-{}", answer.id);
 
     html! {
         div class="p-4 pb-0 mb-2 bg-white"
-            v-scope=(format!("{{show_all: {show_all} }}")) {
+            v-scope=[(!no_js).then(|| format!("{{show_all: {show_all} }}"))] {
+            @if no_js {
+                input type="checkbox" id=(toggle) class="hidden" data-expand="toggle" checked[show_all];
+            }
             @if let Some(author) = &answer.author {
                 div class="flex items-center" {
-                    img class="mr-2 w-8 h-8 object-cover rounded-sm" src=(author.avatar_url) alt=(author.name);
+                    img class="mr-2 w-8 h-8 object-cover rounded-sm" src=(image_url(&author.avatar_url)) alt=(author.name);
                     div {
-                        div class="text-sm" { (author.name) }
+                        a class="text-sm" href=[people_href(author)] { (author.name) }
                         div class="text-xs text-gray-600" { (author.headline) }
                     }
                 }
             }
 
-            div class="relative" v-bind:class="show_all ? '' : 'line-clamp-5 max-h-40'" {
-                (render_html(&content))
+            @if no_js {
+                div class="relative" data-expand="content" {
+                    (render_html(content))
 
-                template v-if="!show_all" {
-                    div v-on:click="show_all = true"
-                        class="absolute cursor-pointer bg-gradient-to-t from-white to-transparent h-full w-full top-0 flex justify-center items-end" {
+                    label for=(toggle)
+                        class="absolute cursor-pointer bg-gradient-to-t from-white to-transparent h-full w-full top-0 flex justify-center items-end" data-expand="more" {
                         div class="text-gray-500" { "展开阅读全文" }
                     }
                 }
+            } @else {
+                div class="relative" v-bind:class="show_all ? '' : 'line-clamp-5 max-h-40'" {
+                    (render_html(content))
+
+                    template v-if="!show_all" {
+                        div v-on:click="show_all = true"
+                            class="absolute cursor-pointer bg-gradient-to-t from-white to-transparent h-full w-full top-0 flex justify-center items-end" {
+                            div class="text-gray-500" { "展开阅读全文" }
+                        }
+                    }
+                }
             }
 
             (attachment(&answer.attachment))
@@ -216,22 +329,27 @@ pub fn answer(answer: &TimelineItem, show_all: bool) -> Markup {
             }
 
             div class="flex text-sm text-gray-500 bg-white p-4 -mx-4"
-                v-bind:class="show_all ? 'sticky bottom-0' : ''" {
+                data-expand=[no_js.then_some("bar")]
+                v-bind:class=[(!no_js).then_some("show_all ? 'sticky bottom-0' : ''")] {
                 @if answer.voteup_count > 0 {
                     span class="mr-2" {
                         (answer.voteup_count) " 赞同"
                     }
                 }
                 @if answer.comment_count > 0 {
-                    a href=(format!("/comment/root/{}{}", answer.id, if answer.type_ == "article" {"?type=articles"} else {""} )) {
+                    a href=(comment_href(answer)) {
                         span class="mr-2" {
                             (answer.comment_count) " 条评论"
                         }
                     }
                 }
 
-                template v-if="show_all" {
-                    button v-on:click="show_all=false" class="ml-auto" { "收起" }
+                @if no_js {
+                    label for=(toggle) class="ml-auto cursor-pointer" data-expand="less" { "收起" }
+                } @else {
+                    template v-if="show_all" {
+                        button v-on:click="show_all=false" class="ml-auto" { "收起" }
+                    }
                 }
             }
         }
@@ -239,6 +357,10 @@ pub fn answer(answer: &TimelineItem, show_all: bool) -> Markup {
 }
 
 pub fn timeline(item: &TimelineItem) -> Markup {
+    if let Some(pin) = &item.pin {
+        return pin_timeline(pin);
+    }
+
     let title = &item
         .title
         .as_ref()
@@ -251,22 +373,11 @@ pub fn timeline(item: &TimelineItem) -> Markup {
         .or(item.content.as_ref())
         .map(String::as_str)
         .unwrap_or_default();
-    let thumbnail = item.thumbnail.as_ref().map(|s| s.as_str());
-    let thumbnail = item
-        .ext
-        .pointer("/thumbnail_info/thumbnails/0/url")
-        .and_then(|v| v.as_str())
-        .or(thumbnail);
-    let (body_href, title_href) = if item.type_ == "answer" {
-        let question = item.question.as_ref().unwrap();
-
-        (
-            format!("/question/{}/answer/{}", question.id, item.id),
-            format!("/question/{}", question.id),
-        )
-    } else {
-        let href = format!("/p/{}", item.id);
-        (href.clone(), href)
+    let thumbnail = item.thumbnail.as_deref();
+    let body_href = href(item);
+    let title_href = match &item.question {
+        Some(question) if item.type_ == "answer" => format!("/question/{}", question.id),
+        _ => body_href.clone(),
     };
 
     html! {
@@ -276,67 +387,355 @@ pub fn timeline(item: &TimelineItem) -> Markup {
                     (render_html(title))
                 }
             }
-            a class="flex" href=(body_href) {
+            div class="flex" {
                 div class="mr-auto" {
                     div class="text-sm line-clamp-3" {
                         @if let Some(author) = item.author.as_ref() {
-                            span class="font-bold" {
-                                (PreEscaped(&author.name))":"
+                            a class="font-bold" href=[people_href(author)] {
+                                (PreEscaped(&author.name))
                             }
+                            ":"
                         }
-                        span {
-                            (render_html(&content))
+                        a href=(body_href) {
+                            (render_html(content))
                         }
                     }
-                    div class="mt-2 text-xs text-gray-500" {
-                        @if item.voteup_count > 0{
-                            span class="mr-2" {
-                                (item.voteup_count) " 赞同"
+                    a href=(body_href) {
+                        div class="mt-2 text-xs text-gray-500" {
+                            @if item.voteup_count > 0{
+                                span class="mr-2" {
+                                    (item.voteup_count) " 赞同"
+                                }
                             }
-                        }
-                        @if  item.comment_count > 0{
-                            span class="mr-2" {
-                                (item.comment_count) " 条评论"
+                            @if  item.comment_count > 0{
+                                span class="mr-2" {
+                                    (item.comment_count) " 条评论"
+                                }
                             }
-                        }
-                        @if let Some(created_time) = item.created_time {
-                            span class="mx-1" {
-                                (time(created_time))
+                            @if let Some(created_time) = item.created_time {
+                                span class="mx-1" {
+                                    (time(created_time))
+                                }
                             }
                         }
                     }
                 }
                 @if let Some(thumbnail) = thumbnail {
-                    img class="flex-grow-0 ml-2 w-auto max-w-[25%] h-16 object-cover rounded" src=(thumbnail);
+                    a class="flex-grow-0 ml-2 max-w-[25%]" href=(body_href) {
+                        img class="w-auto h-16 object-cover rounded" src=(image_url(thumbnail));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn hot_item(item: &HotItem) -> Markup {
+    let href = format!("/question/{}", item.question.id);
+
+    html! {
+        div class="p-4 mb-2 bg-white flex items-start" {
+            @if item.rank <= 3 {
+                div class="flex-shrink-0 w-8 text-lg font-bold text-red-400" { (item.rank) }
+            } @else {
+                div class="flex-shrink-0 w-8 text-lg font-bold text-gray-400" { (item.rank) }
+            }
+            a class="flex flex-grow" href=(href) {
+                div class="mr-auto" {
+                    h3 class="text-base font-bold mb-1" { (item.question.title) }
+                    @if !item.excerpt.is_empty() {
+                        div class="text-sm text-gray-600 line-clamp-2" { (item.excerpt) }
+                    }
+                    div class="mt-2 text-xs text-gray-500" {
+                        @if !item.heat.is_empty() {
+                            span class="mr-2" { (item.heat) }
+                        }
+                        @if item.question.answer_count > 0 {
+                            span class="mr-2" { (item.question.answer_count) " 回答" }
+                        }
+                    }
+                }
+                @if let Some(thumbnail) = &item.thumbnail {
+                    img class="flex-grow-0 ml-2 w-auto max-w-[25%] h-16 object-cover rounded" src=(image_url(thumbnail));
                 }
             }
         }
     }
 }
 
+fn pin_timeline(pin: &Pin) -> Markup {
+    let href = format!("/pin/{}", pin.id);
+
+    html! {
+        div class="p-4 mb-2 bg-white" {
+            div class="text-sm line-clamp-3" {
+                @if let Some(author) = pin.author.as_ref() {
+                    a class="font-bold" href=[people_href(author)] {
+                        (PreEscaped(&author.name))
+                    }
+                    ":"
+                }
+                a href=(href) {
+                    (render_html(&pin.text()))
+                }
+            }
+            (pin_images(pin, 3))
+            @if let Some(origin) = &pin.origin_pin {
+                div class="mt-2 p-2 bg-gray-100 rounded text-sm line-clamp-3" {
+                    @if let Some(author) = origin.author.as_ref() {
+                        a class="font-bold" href=[people_href(author)] { "@" (author.name) }
+                        ":"
+                    }
+                    a href=(format!("/pin/{}", origin.id)) { (render_html(&origin.text())) }
+                }
+            }
+            a href=(href) {
+                div class="mt-2 text-xs text-gray-500" {
+                    span class="mr-2" { "想法" }
+                    @if pin.likes() > 0 {
+                        span class="mr-2" { (pin.likes()) " 赞" }
+                    }
+                    @if pin.comment_count > 0 {
+                        span class="mr-2" { (pin.comment_count) " 条评论" }
+                    }
+                    @if let Some(created) = pin.created {
+                        span class="mx-1" { (time(created)) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Images of a pin, a single one at full width and several in a square grid.
+/// Only the first `limit` are shown.
+fn pin_images(pin: &Pin, limit: usize) -> Markup {
+    let images: Vec<(&str, &str, u32)> = pin
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            PinContent::Image {
+                url,
+                original_url,
+                width,
+                ..
+            } if !url.is_empty() => {
+                let original = if original_url.is_empty() {
+                    url
+                } else {
+                    original_url
+                };
+                Some((url.as_str(), original.as_str(), *width))
+            }
+            _ => None,
+        })
+        .collect();
+
+    html! {
+        @if let [(url, original, width)] = images[..] {
+            @let srcset = srcset(original, (width > 0).then_some(width));
+            a class="block mt-2" target="_blank" href=(image_url(original)) {
+                img class="mx-auto max-h-96" loading="lazy" src=(image_url(url))
+                    srcset=[&srcset] sizes=[srcset.as_ref().map(|_| resize::SIZES)];
+            }
+        } @else if !images.is_empty() {
+            div class="mt-2 grid grid-cols-3 gap-1" {
+                @for (url, original, _) in images.iter().take(limit) {
+                    a target="_blank" href=(image_url(original)) {
+                        img class="w-full aspect-square object-cover rounded-sm" loading="lazy" src=(image_url(url));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A pin with all its images and the pin it reposts.
+pub fn pin(pin: &Pin) -> Markup {
+    html! {
+        div class="p-4 mb-2 bg-white" {
+            @if let Some(author) = &pin.author {
+                div class="flex items-center" {
+                    img class="mr-2 w-8 h-8 object-cover rounded-sm" src=(image_url(&author.avatar_url)) alt=(author.name);
+                    div {
+                        a class="text-sm" href=[people_href(author)] { (author.name) }
+                        div class="text-xs text-gray-600" { (author.headline) }
+                    }
+                }
+            }
+
+            div { (render_html(&pin.text())) }
+            (pin_images(pin, 9))
+
+            @if let Some(origin) = &pin.origin_pin {
+                div class="mt-2 p-2 bg-gray-100 rounded" {
+                    @if let Some(author) = &origin.author {
+                        a class="text-sm font-bold" href=[people_href(author)] { "@" (author.name) }
+                    }
+                    a class="block" href=(format!("/pin/{}", origin.id)) {
+                        (render_html(&origin.text()))
+                    }
+                    (pin_images(origin, 9))
+                }
+            }
+
+            div class="flex text-sm text-gray-500 mt-2 py-4" {
+                @if pin.likes() > 0 {
+                    span class="mr-2" { (pin.likes()) " 赞" }
+                }
+                @if pin.comment_count > 0 {
+                    a href=(format!("/comment/root/{}?type=pins", pin.id)) {
+                        span class="mr-2" { (pin.comment_count) " 条评论" }
+                    }
+                }
+                @if pin.repin_count > 0 {
+                    span class="mr-2" { (pin.repin_count) " 转发" }
+                }
+                @if let Some(time) = pin.updated.or(pin.created) {
+                    span class="ml-auto text-gray-400" { (self::time(time)) }
+                }
+            }
+        }
+    }
+}
+
+/// Our profile page of the author, `None` for anonymous users.
+pub fn people_href(author: &Author) -> Option<String> {
+    (!author.url_token.is_empty()).then(|| format!("/people/{}", author.url_token))
+}
+
+/// Header of a profile page.
+pub fn people(people: &People) -> Markup {
+    html! {
+        div class="p-4 mb-2 bg-white" {
+            div class="flex items-center" {
+                img class="mr-2 w-16 h-16 object-cover rounded" src=(image_url(&people.avatar_url)) alt=(people.name);
+                div {
+                    h2 class="text-xl font-bold" { (people.name) }
+                    div class="text-sm text-gray-600" { (people.headline) }
+                }
+            }
+            @if !people.description.is_empty() {
+                div class="mt-2 text-sm text-gray-600" { (render_html(&people.description)) }
+            }
+            div class="mt-2 text-sm text-gray-500" {
+                span class="mr-2" { (people.follower_count) " 关注者" }
+                span class="mr-2" { (people.answer_count) " 回答" }
+                span class="mr-2" { (people.articles_count) " 文章" }
+                @if people.pins_count > 0 {
+                    span class="mr-2" { (people.pins_count) " 想法" }
+                }
+            }
+        }
+    }
+}
+
+/// Header of a column page.
+pub fn column(column: &Column) -> Markup {
+    html! {
+        div class="p-4 mb-2 bg-white" {
+            div class="flex items-center" {
+                @if !column.image_url.is_empty() {
+                    img class="mr-2 w-16 h-16 object-cover rounded" src=(image_url(&column.image_url)) alt=(column.title);
+                }
+                div {
+                    h2 class="text-xl font-bold" { (column.title) }
+                    @if !column.intro.is_empty() {
+                        div class="text-sm text-gray-600" { (column.intro) }
+                    }
+                }
+            }
+            @if !column.description.is_empty() {
+                div class="mt-2 text-sm text-gray-600" { (render_html(&column.description)) }
+            }
+            div class="mt-2 text-sm text-gray-500" {
+                @if let Some(author) = &column.author {
+                    a class="mr-2" href=[people_href(author)] { (author.name) }
+                }
+                span class="mr-2" { (column.articles_count) " 篇文章" }
+                @if column.followers > 0 {
+                    span class="mr-2" { (column.followers) " 关注" }
+                }
+            }
+        }
+    }
+}
+
+/// Our page showing an answer, article, pin or video.
+pub fn href(item: &TimelineItem) -> String {
+    match &item.question {
+        Some(question) if item.type_ == "answer" => {
+            format!("/question/{}/answer/{}", question.id, item.id)
+        }
+        _ if item.type_ == "pin" => format!("/pin/{}", item.id),
+        _ if item.type_ == "zvideo" => format!("/zvideo/{}", item.id),
+        _ => format!("/p/{}", item.id),
+    }
+}
+
+/// The answer, article, pin or video on zhihu, does not depend on where we are hosted.
+pub fn zhihu_url(item: &TimelineItem) -> String {
+    match &item.question {
+        Some(question) if item.type_ == "answer" => format!(
+            "https://www.zhihu.com/question/{}/answer/{}",
+            question.id, item.id
+        ),
+        _ if item.type_ == "pin" => format!("https://www.zhihu.com/pin/{}", item.id),
+        _ if item.type_ == "zvideo" => format!("https://www.zhihu.com/zvideo/{}", item.id),
+        _ => format!("https://zhuanlan.zhihu.com/p/{}", item.id),
+    }
+}
+
+/// Our comments page of an answer, article, pin or video.
+pub fn comment_href(item: &TimelineItem) -> String {
+    match item.type_.as_str() {
+        "article" => format!("/comment/root/{}?type=articles", item.id),
+        "pin" => format!("/comment/root/{}?type=pins", item.id),
+        "zvideo" => format!("/comment/root/{}?type=zvideos", item.id),
+        _ => format!("/comment/root/{}", item.id),
+    }
+}
+
 pub fn question(question: &Question, show_all: bool) -> Markup {
     let has_detail = !question.detail.is_empty();
+    let no_js = no_js();
+    let toggle = format!("expand-question-{}", question.id);
 
     html! {
         div class="p-4 pb-0 my-1 bg-white"
-            v-scope=(format!("{{show_all: {show_all} }}")) {
+            v-scope=[(!no_js).then(|| format!("{{show_all: {show_all} }}"))] {
+            @if no_js && has_detail {
+                input type="checkbox" id=(toggle) class="hidden" data-expand="toggle" checked[show_all];
+            }
             h3 class="text-base font-bold text-lg" { (question.title )}
 
             @if has_detail {
-                div class="relative text-sm text-gray-600" v-bind:class="show_all ? '' : 'line-clamp-5 max-h-40'" {
-                    (render_html(&question.detail))
+                @if no_js {
+                    div class="relative text-sm text-gray-600" data-expand="content" {
+                        (render_html(&question.detail))
 
-                    template v-if="!show_all" {
-                        div v-on:click="show_all = true"
-                            class="absolute cursor-pointer bg-gradient-to-t from-white to-transparent h-full w-full top-0 flex justify-center items-end" {
+                        label for=(toggle)
+                            class="absolute cursor-pointer bg-gradient-to-t from-white to-transparent h-full w-full top-0 flex justify-center items-end" data-expand="more" {
                             div class="text-gray-500" { "展开阅读全文" }
                         }
                     }
+                } @else {
+                    div class="relative text-sm text-gray-600" v-bind:class="show_all ? '' : 'line-clamp-5 max-h-40'" {
+                        (render_html(&question.detail))
+
+                        template v-if="!show_all" {
+                            div v-on:click="show_all = true"
+                                class="absolute cursor-pointer bg-gradient-to-t from-white to-transparent h-full w-full top-0 flex justify-center items-end" {
+                                div class="text-gray-500" { "展开阅读全文" }
+                            }
+                        }
+                    }
                 }
             }
 
             div class="flex text-sm text-gray-400 bg-white p-4 -mx-4"
-                v-bind:class="show_all ? 'sticky bottom-0' : ''" {
+                data-expand=[no_js.then_some("bar")]
+                v-bind:class=[(!no_js).then_some("show_all ? 'sticky bottom-0' : ''")] {
                 @if question.answer_count > 0 {
                     span class="mr-2" { (question.answer_count) " 回答" }
                 }
@@ -350,8 +749,12 @@ pub fn question(question: &Question, show_all: bool) -> Markup {
                 }
 
                 @if has_detail {
-                    template v-if="show_all" {
-                        button v-on:click="show_all=false" class="ml-auto" { "收起" }
+                    @if no_js {
+                        label for=(toggle) class="ml-auto cursor-pointer" data-expand="less" { "收起" }
+                    } @else {
+                        template v-if="show_all" {
+                            button v-on:click="show_all=false" class="ml-auto" { "收起" }
+                        }
                     }
                 }
             }
@@ -362,17 +765,18 @@ pub fn question(question: &Question, show_all: bool) -> Markup {
 pub fn comment(comment: &Comment, show_more: bool) -> Markup {
     html! {
         div class="flex items-start" {
-            img class="flex-shrink-0 w-8 h-8 rounded-sm object-cover mr-2" src=(comment.author.avatar_url) alt=(comment.author.name);
+            img class="flex-shrink-0 w-8 h-8 rounded-sm object-cover mr-2" src=(image_url(&comment.author.avatar_url)) alt=(comment.author.name);
             div class="flex-grow" {
                 div class="flex items-center font-bold" {
-                    (comment.author.name)
+                    a href=[people_href(&comment.author)] { (comment.author.name) }
                     @for tag in &comment.author_tag {
                         span class="font-normal text-gray-400 text-xs mx-1 border-1 px-1 rounded-sm" {
                             (tag.text)
                         }
                     }
                     @if let Some(reply_to_author) = comment.reply_to_author.as_ref() {
-                        " > " (reply_to_author.name)
+                        " > "
+                        a href=[people_href(reply_to_author)] { (reply_to_author.name) }
                     }
                 }
                 div {
@@ -438,7 +842,173 @@ pub fn relevant_query(querys: &Value) -> Markup {
     }
 }
 
+pub fn archived(archived_at: Option<i64>) -> Markup {
+    html! {
+        @if let Some(archived_at) = archived_at {
+            div class="p-4 mb-2 bg-yellow-100 text-sm text-yellow-800" {
+                "知乎暂时无法访问或内容已被删除, 以下是 " (time(archived_at)) " 的存档"
+            }
+        }
+    }
+}
+
+pub fn health_banner(health: &CookieHealth) -> Markup {
+    html! {
+        @if health.state == HealthState::Invalid {
+            a href="/status" {
+                div class="p-4 mb-2 bg-red-100 text-sm text-red-800" {
+                    "知乎 cookie 已失效, 页面内容可能为空或不完整, 请更新 ZHIHU_COOKIE (" (health.message) ")"
+                }
+            }
+        } @else if health.state == HealthState::Throttled {
+            a href="/status" {
+                div class="p-4 mb-2 bg-yellow-100 text-sm text-yellow-800" {
+                    "知乎正在限流, 页面可能加载失败, 请稍后再试 (" (health.message) ")"
+                }
+            }
+        }
+    }
+}
+
+pub fn datetime(seconds: i64) -> String {
+    let time = chrono::NaiveDateTime::from_timestamp_opt(seconds, 0).unwrap();
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub fn rfc3339(seconds: i64) -> String {
+    Utc.timestamp_opt(seconds, 0)
+        .single()
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
 pub fn time(seconds: i64) -> String {
     let time = chrono::NaiveDateTime::from_timestamp_opt(seconds, 0).unwrap();
     time.format("%Y-%m-%d").to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        self,
+        tests::{fixture, fixture_json},
+    };
+
+    #[test]
+    fn timeline_answer_and_article() {
+        let results = parser::parse_timeline(&fixture_json(
+            "www.zhihu.com/api/v3/feed/topstory/recommend.json",
+        ));
+
+        insta::assert_snapshot!(timeline(&results.data[0]).into_string());
+        insta::assert_snapshot!(timeline(&results.data[1]).into_string());
+    }
+
+    #[test]
+    fn hot_list_item() {
+        let results = parser::parse_hot_list(&fixture_json(
+            "www.zhihu.com/api/v3/feed/topstory/hot-lists/total.json",
+        ));
+
+        insta::assert_snapshot!(hot_item(&results.data[0]).into_string());
+    }
+
+    #[test]
+    fn pin_timeline_and_page() {
+        let results = parser::parse_timeline(&fixture_json(
+            "www.zhihu.com/api/v3/feed/topstory/recommend.json",
+        ));
+        insta::assert_snapshot!(timeline(&results.data[2]).into_string());
+
+        let p: Pin =
+            serde_json::from_value(fixture_json("www.zhihu.com/api/v4/pins/4000001.json")).unwrap();
+        insta::assert_snapshot!(pin(&p).into_string());
+    }
+
+    #[test]
+    fn srcset_keeps_original() {
+        let url = "https://pic1.zhimg.com/v2-figure.jpg";
+        let largest = |srcset: String| {
+            let last = srcset.rsplit(", ").next().unwrap().to_string();
+            last.rsplit(' ').next().unwrap().to_string()
+        };
+
+        assert_eq!(largest(srcset(url, Some(1000)).unwrap()), "1000w");
+        assert_eq!(largest(srcset(url, Some(400)).unwrap()), "400w");
+        assert!(srcset(url, Some(400))
+            .unwrap()
+            .ends_with("/img?url=https%3A%2F%2Fpic1.zhimg.com%2Fv2-figure.jpg 400w"));
+        assert_eq!(largest(srcset(url, None).unwrap()), "1440w");
+        assert_eq!(srcset(url, Some(300)), None);
+    }
+
+    #[test]
+    fn zvideo_links() {
+        let html = render_html(concat!(
+            r#"<a href="https://www.zhihu.com/zvideo/3000001?utm_id=0">视频</a>"#,
+            r#"<a href="https://zhihu.com/zvideo/3000002">视频</a>"#,
+            r#"<a href="https://link.zhihu.com/?target=https%3A//www.zhihu.com/zvideo/3000003">视频</a>"#,
+            r#"<a href="https://www.zhihu.com/question/1">问题</a>"#,
+        ))
+        .into_string();
+
+        let hrefs: Vec<&str> = html
+            .split(r#"href=""#)
+            .skip(1)
+            .map(|rest| rest.split('"').next().unwrap())
+            .collect();
+        assert_eq!(
+            hrefs,
+            [
+                "/zvideo/3000001",
+                "/zvideo/3000002",
+                "/zvideo/3000003",
+                "/question/1"
+            ]
+        );
+    }
+
+    #[test]
+    fn answer_content() {
+        let results = parser::parse_timeline(&fixture_json(
+            "www.zhihu.com/api/v4/questions/19550225/feeds.json",
+        ));
+
+        insta::assert_snapshot!(answer(&results.data[0], true).into_string());
+    }
+
+    #[test]
+    fn answer_without_js() {
+        let results = parser::parse_timeline(&fixture_json(
+            "www.zhihu.com/api/v4/questions/19550225/feeds.json",
+        ));
+
+        let html = NO_JS.sync_scope(true, || answer(&results.data[0], false).into_string());
+        assert!(!html.contains("v-scope") && !html.contains("<template"));
+        assert!(html.contains(
+            r#"<input type="checkbox" id="expand-answer-1000001" class="hidden" data-expand="toggle">"#
+        ));
+        assert!(html.contains(r#"<label for="expand-answer-1000001" class="ml-auto cursor-pointer" data-expand="less">收起</label>"#));
+
+        let html = NO_JS.sync_scope(true, || answer(&results.data[0], true).into_string());
+        assert!(html.contains(r#"data-expand="toggle" checked>"#));
+    }
+
+    #[test]
+    fn article_content() {
+        let html = fixture("zhuanlan.zhihu.com/p/2000001.html");
+        let article: TimelineItem =
+            parser::parse_entity_data(&html, "articles", "2000001").unwrap();
+
+        insta::assert_snapshot!(answer(&article, true).into_string());
+    }
+
+    #[test]
+    fn question_detail() {
+        let html = fixture("www.zhihu.com/question/19550225.html");
+        let q: Question = parser::parse_entity_data(&html, "questions", "19550225").unwrap();
+
+        insta::assert_snapshot!(question(&q, false).into_string());
+    }
+}